@@ -0,0 +1,87 @@
+use std::{convert::Infallible, str::FromStr};
+
+use midly::{MetaMessage, Smf, TrackEventKind};
+
+/// Picks out a track by its index in the file, or by its `MetaMessage::TrackName`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackSelector {
+    Index(usize),
+    /// Compared ignoring ASCII case and surrounding whitespace
+    Name(String),
+}
+
+impl TrackSelector {
+    fn matches(&self, index: usize, name: Option<&str>) -> bool {
+        match self {
+            TrackSelector::Index(i) => *i == index,
+            TrackSelector::Name(wanted) => name.is_some_and(|name| name.eq_ignore_ascii_case(wanted.trim())),
+        }
+    }
+}
+
+impl FromStr for TrackSelector {
+    type Err = Infallible;
+
+    /// Numbers are track indices, anything else is a track name.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.parse() {
+            Ok(index) => TrackSelector::Index(index),
+            Err(_) => TrackSelector::Name(s.to_owned()),
+        })
+    }
+}
+
+/// Which tracks and channels to mute or solo.
+///
+/// If anything is soloed, only soloed tracks (or channels) are heard. Mutes apply on top of that.
+/// Only channel messages are filtered; meta and system exclusive events always pass.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    pub muted_tracks: Vec<TrackSelector>,
+    pub soloed_tracks: Vec<TrackSelector>,
+    /// Channels numbered from 0, as in `midly`
+    pub muted_channels: Vec<u8>,
+    /// Channels numbered from 0, as in `midly`
+    pub soloed_channels: Vec<u8>,
+}
+
+impl Filter {
+    pub(crate) fn resolve(&self, midi: &Smf) -> Audible {
+        let tracks = midi.tracks.iter().enumerate().map(|(index, track)| {
+            let name = track.iter().find_map(|event| match event.kind {
+                TrackEventKind::Meta(MetaMessage::TrackName(name)) => Some(String::from_utf8_lossy(name)),
+                _ => None,
+            });
+            let name = name.as_deref().map(str::trim);
+            let soloed = self.soloed_tracks.is_empty()
+                || self.soloed_tracks.iter().any(|selector| selector.matches(index, name));
+            let muted = self.muted_tracks.iter().any(|selector| selector.matches(index, name));
+            soloed && !muted
+        }).collect();
+        let channels = std::array::from_fn(|channel| {
+            let channel = channel as u8;
+            let soloed = self.soloed_channels.is_empty() || self.soloed_channels.contains(&channel);
+            soloed && !self.muted_channels.contains(&channel)
+        });
+        Audible { tracks, channels }
+    }
+}
+
+/// A `Filter` resolved against a particular file.
+#[derive(Debug, Clone)]
+pub(crate) struct Audible {
+    tracks: Vec<bool>,
+    channels: [bool; 16],
+}
+
+impl Audible {
+    pub(crate) fn allows(&self, track_index: usize, event: &TrackEventKind) -> bool {
+        match event {
+            TrackEventKind::Midi { channel, .. } => {
+                self.tracks.get(track_index).copied().unwrap_or(true)
+                    && self.channels[usize::from(channel.as_int())]
+            }
+            _ => true,
+        }
+    }
+}
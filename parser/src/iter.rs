@@ -0,0 +1,354 @@
+use std::{collections::HashMap, iter::Peekable, time::Duration};
+
+use midly::{MetaMessage, MidiMessage, PitchBend, Smf, TrackEvent, TrackEventKind};
+
+use crate::{Position, TempoMap, TrackLayout};
+
+/// Time since the start of the song, absolute tick, index of the track, and the event itself.
+pub type TimedEvent<'a> = (Duration, u64, usize, TrackEventKind<'a>);
+
+/// Controllers that Reset All Controllers sets back to their defaults, as in RP-015: modulation,
+/// expression and the pedals. Volume, pan and bank select are left alone.
+const RESET_CONTROLLERS: [u8; 6] = [1, 11, 64, 65, 66, 67];
+
+/// Value of a controller before anything sets it
+fn controller_default(controller: u8) -> u8 {
+    match controller {
+        // Volume
+        7 => 100,
+        // Balance and pan
+        8 | 10 => 64,
+        // Expression
+        11 => 127,
+        _ => 0,
+    }
+}
+
+/// Merges all tracks of a file into a single timeline, in time order, without waiting.
+///
+/// Events at the same tick are yielded in track order.
+pub struct MidiEventIter<'a> {
+    midi: &'a Smf<'a>,
+    layout: TrackLayout,
+    tempo_map: TempoMap,
+    // Absolute tick of next event, track index, remaining events.
+    // Use .remove() when the iterator is empty
+    progress: Vec<(u64, usize, Peekable<std::slice::Iter<'a, TrackEvent<'a>>>)>,
+}
+
+impl<'a> MidiEventIter<'a> {
+    pub fn new(midi: &'a Smf<'a>) -> Self {
+        Self::with_layout(midi, TrackLayout::for_format(midi.header.format))
+    }
+
+    pub fn with_layout(midi: &'a Smf<'a>, layout: TrackLayout) -> Self {
+        let mut this = Self {
+            midi,
+            layout,
+            tempo_map: TempoMap::with_layout(midi, layout),
+            progress: vec![],
+        };
+        this.rewind();
+        this
+    }
+
+    /// Go back to the start of the song.
+    fn rewind(&mut self) {
+        let midi = self.midi;
+        self.progress = self.layout.offsets(midi).into_iter().flat_map(
+            |(track_index, offset)| {
+                let track = &midi.tracks[track_index];
+                let first = track.first()?;
+                let tick = offset + u64::from(u32::from(first.delta));
+                Some((tick, track_index, track.iter().peekable()))
+            }
+        ).collect();
+    }
+
+    /// Moves to the given absolute tick, so that the next event is the first one at or after it.
+    ///
+    /// Returns the "state" events that were skipped over and still apply at `tick`
+    /// (the latest program, controller values, pitch bend and channel pressure of each channel,
+    /// and the tempo), in the order they last happened, timed at `tick`.
+    ///
+    /// Registered and non-registered parameters are chased as a whole: each one that was set is
+    /// selected again and given its data entry, and then the parameter that was last selected is
+    /// selected. The last Reset All Controllers of each channel is chased too, in its place.
+    pub fn seek(&mut self, tick: u64) -> Vec<TimedEvent<'a>> {
+        // Always start over, since state from before the current position still applies.
+        self.rewind();
+
+        #[derive(PartialEq, Eq, Hash)]
+        enum State {
+            Program(u8),
+            Controller(u8, u8),
+            PitchBend(u8),
+            ChannelAftertouch(u8),
+            Tempo,
+            /// Data entry for one parameter of a channel
+            Parameter(u8, Parameter),
+            /// Which parameter of a channel data entry applies to
+            Selection(u8),
+            ResetAllControllers(u8),
+        }
+
+        enum Chased<'a> {
+            Event(usize, TrackEventKind<'a>),
+            /// Filled in from `parameters` once the last data entry for it is known
+            Parameter(u8, Parameter),
+            /// Filled in from `selections`, from the track of the last selection
+            Selection(usize, u8),
+        }
+
+        // Chased events in order, with `None` where an event was superseded by a later one.
+        let mut chased = vec![];
+        let mut latest = HashMap::new();
+        let mut selections = [Parameter::NULL; 16];
+        // Data entry events for each parameter, since the last absolute value
+        let mut parameters: HashMap<(u8, Parameter), Vec<(usize, TrackEventKind<'a>)>> = HashMap::new();
+        while self.peek_tick().is_some_and(|next| next < tick) {
+            let (_, _, track_index, kind) = self.next().expect("peeked");
+            let (state, event) = match kind {
+                TrackEventKind::Midi { channel, message } => {
+                    let channel = channel.as_int();
+                    match message {
+                        MidiMessage::ProgramChange { .. } => (State::Program(channel), Chased::Event(track_index, kind)),
+                        MidiMessage::PitchBend { .. } => (State::PitchBend(channel), Chased::Event(track_index, kind)),
+                        MidiMessage::ChannelAftertouch { .. } => (State::ChannelAftertouch(channel), Chased::Event(track_index, kind)),
+                        MidiMessage::Controller { controller, value } => match controller.as_int() {
+                            // Reset All Controllers, which is chased itself, so that the receiver
+                            // also resets anything else it holds
+                            121 => {
+                                latest.retain(|state, &mut index: &mut usize| {
+                                    let reset = match *state {
+                                        State::Controller(c, controller) => c == channel && RESET_CONTROLLERS.contains(&controller),
+                                        State::PitchBend(c) | State::ChannelAftertouch(c) | State::Selection(c) => c == channel,
+                                        _ => false,
+                                    };
+                                    if reset {
+                                        chased[index] = None;
+                                    }
+                                    !reset
+                                });
+                                selections[usize::from(channel)] = Parameter::NULL;
+                                (State::ResetAllControllers(channel), Chased::Event(track_index, kind))
+                            }
+                            // Channel mode messages that do not change any state
+                            120 | 122..=127 => continue,
+                            // Data entry, increment and decrement
+                            6 | 38 | 96 | 97 => {
+                                let parameter = selections[usize::from(channel)];
+                                if parameter == Parameter::NULL {
+                                    continue;
+                                }
+                                let data = parameters.entry((channel, parameter)).or_default();
+                                // Data entry MSB sets the whole value anew
+                                if controller == 6 {
+                                    data.clear();
+                                }
+                                data.push((track_index, kind));
+                                (State::Parameter(channel, parameter), Chased::Parameter(channel, parameter))
+                            }
+                            select @ 98..=101 => {
+                                let selection = &mut selections[usize::from(channel)];
+                                *selection = selection.select(select, value.as_int());
+                                (State::Selection(channel), Chased::Selection(track_index, channel))
+                            }
+                            controller => (State::Controller(channel, controller), Chased::Event(track_index, kind)),
+                        },
+                        MidiMessage::NoteOn { .. } | MidiMessage::NoteOff { .. } | MidiMessage::Aftertouch { .. } => continue,
+                    }
+                }
+                TrackEventKind::Meta(MetaMessage::Tempo(..)) => (State::Tempo, Chased::Event(track_index, kind)),
+                _ => continue,
+            };
+            if let Some(index) = latest.insert(state, chased.len()) {
+                chased[index] = None;
+            }
+            chased.push(Some(event));
+        }
+
+        let time = self.tempo_map.tick_to_time(tick);
+        let mut events = vec![];
+        for event in chased.into_iter().flatten() {
+            match event {
+                Chased::Event(track_index, kind) => events.push((track_index, kind)),
+                Chased::Parameter(channel, parameter) => {
+                    let data = &parameters[&(channel, parameter)];
+                    let (track_index, _) = data[0];
+                    events.extend(parameter.select_events(channel).map(|kind| (track_index, kind)));
+                    events.extend(data.iter().copied());
+                }
+                Chased::Selection(track_index, channel) => {
+                    let selection = selections[usize::from(channel)];
+                    events.extend(selection.select_events(channel).map(|kind| (track_index, kind)));
+                }
+            }
+        }
+        events.into_iter().map(|(track_index, kind)| (time, tick, track_index, kind)).collect()
+    }
+
+    /// Events that set back to its default each controller, pitch bend and channel pressure that
+    /// changes from `start` up to `end`, timed at `start`. Sent when a loop wraps, these let go of
+    /// pedals that were pressed inside the loop, before the state at `start` is chased.
+    pub fn defaults(&self, start: u64, end: u64) -> Vec<TimedEvent<'a>> {
+        let mut events = Self::with_layout(self.midi, self.layout);
+        events.seek(start);
+        let mut defaults = vec![];
+        while events.peek_tick().is_some_and(|next| next < end) {
+            let (_, _, track_index, kind) = events.next().expect("peeked");
+            let TrackEventKind::Midi { channel, message } = kind else {
+                continue;
+            };
+            let message = match message {
+                MidiMessage::Controller { controller, .. } => match controller.as_int() {
+                    // Bank select, parameters and channel mode messages have no default to go back to
+                    0 | 32 | 6 | 38 | 96..=101 | 120..=127 => continue,
+                    controller => MidiMessage::Controller { controller: controller.into(), value: controller_default(controller).into() },
+                },
+                MidiMessage::PitchBend { .. } => MidiMessage::PitchBend { bend: PitchBend::from_int(0) },
+                MidiMessage::ChannelAftertouch { .. } => MidiMessage::ChannelAftertouch { vel: 0.into() },
+                _ => continue,
+            };
+            let default = TrackEventKind::Midi { channel, message };
+            if !defaults.iter().any(|(_, event)| *event == default) {
+                defaults.push((track_index, default));
+            }
+        }
+        let time = self.tempo_map.tick_to_time(start);
+        defaults.into_iter().map(|(track_index, kind)| (time, start, track_index, kind)).collect()
+    }
+
+    pub fn tempo_map(&self) -> &TempoMap {
+        &self.tempo_map
+    }
+
+    pub fn midi(&self) -> &'a Smf<'a> {
+        self.midi
+    }
+
+    /// Absolute tick at which the last track ends.
+    pub fn end_tick(&self) -> u64 {
+        self.layout.offsets(self.midi).into_iter().map(|(track_index, offset)| {
+            offset + self.midi.tracks[track_index].iter().map(|event| u64::from(u32::from(event.delta))).sum::<u64>()
+        }).max().unwrap_or(0)
+    }
+
+    /// Absolute tick and text of every `MetaMessage::Marker`, in time order.
+    pub fn markers(&self) -> Vec<(u64, &'a [u8])> {
+        Self::with_layout(self.midi, self.layout).filter_map(|(_, tick, _, kind)| match kind {
+            TrackEventKind::Meta(MetaMessage::Marker(text)) => Some((tick, text)),
+            _ => None,
+        }).collect()
+    }
+
+    /// Absolute tick of the given position, if it can be found in this file.
+    pub fn resolve(&self, position: &Position) -> Option<u64> {
+        match position {
+            Position::Tick(tick) => Some(*tick),
+            Position::Time(time) => Some(self.tempo_map.time_to_tick(*time)),
+            Position::Bar(bar) => self.tempo_map.bar_to_tick(*bar),
+            Position::Marker(name) => self.markers().into_iter().find_map(|(tick, text)| {
+                (String::from_utf8_lossy(text).trim() == name.trim()).then_some(tick)
+            }),
+        }
+    }
+
+    /// The loop region given by "loopStart" and "loopEnd" markers, a convention in game music.
+    /// Case, spaces and underscores are ignored. Without a "loopEnd", the loop ends with the song.
+    pub fn loop_markers(&self) -> Option<(u64, u64)> {
+        let markers = self.markers();
+        let find = |wanted: &str| markers.iter().find_map(|(tick, text)| {
+            let text: String = String::from_utf8_lossy(text)
+                .chars()
+                .filter(|c| !c.is_whitespace() && *c != '_')
+                .collect();
+            text.eq_ignore_ascii_case(wanted).then_some(*tick)
+        });
+        let start = find("loopStart")?;
+        let end = find("loopEnd").unwrap_or_else(|| self.end_tick());
+        (start < end).then_some((start, end))
+    }
+
+    /// Index into `progress` of the track with the earliest next event.
+    /// Ties go to the lowest track index.
+    fn next_track(&self) -> Option<usize> {
+        self.progress
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, (tick, track_index, _))| (*tick, *track_index))
+            .map(|(i, _)| i)
+    }
+
+    /// Absolute tick of the next event, if any.
+    pub fn peek_tick(&self) -> Option<u64> {
+        self.next_track().map(|i| self.progress[i].0)
+    }
+
+    /// Time since the start of the song of the next event, if any.
+    pub fn peek_time(&self) -> Option<Duration> {
+        self.peek_tick().map(|tick| self.tempo_map.tick_to_time(tick))
+    }
+}
+
+/// A registered (RPN) or non-registered (NRPN) parameter number, as its MSB and LSB
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Parameter {
+    Registered(u8, u8),
+    NonRegistered(u8, u8),
+}
+
+impl Parameter {
+    /// The null RPN, which selects no parameter
+    const NULL: Parameter = Parameter::Registered(127, 127);
+
+    /// The parameter selected after controller 98 to 101 is set to `value`.
+    fn select(self, controller: u8, value: u8) -> Self {
+        let (msb, lsb) = match self {
+            Parameter::Registered(msb, lsb) if controller >= 100 => (msb, lsb),
+            Parameter::NonRegistered(msb, lsb) if controller < 100 => (msb, lsb),
+            // Switching between RPN and NRPN starts from a blank number
+            _ => (127, 127),
+        };
+        match controller {
+            101 => Parameter::Registered(value, lsb),
+            100 => Parameter::Registered(msb, value),
+            99 => Parameter::NonRegistered(value, lsb),
+            _ => Parameter::NonRegistered(msb, value),
+        }
+    }
+
+    /// Controller events that select this parameter on `channel`
+    fn select_events<'a>(self, channel: u8) -> impl Iterator<Item = TrackEventKind<'a>> {
+        let ((msb_controller, msb), (lsb_controller, lsb)) = match self {
+            Parameter::Registered(msb, lsb) => ((101, msb), (100, lsb)),
+            Parameter::NonRegistered(msb, lsb) => ((99, msb), (98, lsb)),
+        };
+        [(msb_controller, msb), (lsb_controller, lsb)].into_iter().map(move |(controller, value)| TrackEventKind::Midi {
+            channel: channel.into(),
+            message: MidiMessage::Controller { controller: controller.into(), value: value.into() },
+        })
+    }
+}
+
+impl<'a> Iterator for MidiEventIter<'a> {
+    type Item = TimedEvent<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let i = self.next_track()?;
+        let (tick, track_index, events) = &mut self.progress[i];
+        let (event_tick, track_index) = (*tick, *track_index);
+        let event = events.next().expect("empty track should have been removed already");
+        match events.peek() {
+            Some(next) => {
+                // Set the new event time
+                *tick += u64::from(u32::from(next.delta));
+            },
+            None => {
+                // If this track has no more events, remove it
+                let _ = self.progress.remove(i);
+            },
+        }
+        Some((self.tempo_map.tick_to_time(event_tick), event_tick, track_index, event.kind))
+    }
+}
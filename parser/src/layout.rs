@@ -0,0 +1,39 @@
+use midly::{Format, Smf};
+
+/// How the tracks of a file are placed in time relative to each other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackLayout {
+    /// All tracks play at the same time. Correct for `Format::SingleTrack` and `Format::Parallel`.
+    Parallel,
+    /// Each track is an independent pattern, played after the previous one ends.
+    /// Correct for `Format::Sequential`.
+    Sequential,
+    /// Only the track with this index plays, from the start of the song.
+    /// Mostly useful to pick one pattern out of a `Format::Sequential` file,
+    /// since it only uses the tempo events in that track.
+    Single(usize),
+}
+
+impl TrackLayout {
+    /// The layout that the file's format calls for.
+    pub fn for_format(format: Format) -> Self {
+        match format {
+            Format::SingleTrack | Format::Parallel => TrackLayout::Parallel,
+            Format::Sequential => TrackLayout::Sequential,
+        }
+    }
+
+    /// Index and starting tick of each track that plays.
+    pub(crate) fn offsets(self, midi: &Smf) -> Vec<(usize, u64)> {
+        match self {
+            TrackLayout::Parallel => (0..midi.tracks.len()).map(|i| (i, 0)).collect(),
+            TrackLayout::Sequential => midi.tracks.iter().enumerate().scan(0u64, |offset, (i, track)| {
+                let start = *offset;
+                *offset += track.iter().map(|event| u64::from(u32::from(event.delta))).sum::<u64>();
+                Some((i, start))
+            }).collect(),
+            TrackLayout::Single(i) if i < midi.tracks.len() => vec![(i, 0)],
+            TrackLayout::Single(_) => vec![],
+        }
+    }
+}
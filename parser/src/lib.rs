@@ -1,38 +1,166 @@
-use std::{pin::Pin, future::Future, task::{Context, Poll}, iter::Peekable, time::Duration};
+use std::{pin::Pin, future::Future, task::{Context, Poll}, collections::VecDeque, time::Duration};
 
 use futures_core::ready;
-use midly::{Smf, TrackEvent};
-use tokio::time::Sleep;
+use midly::{Smf, TrackEventKind};
+use tokio::time::{Instant, Sleep};
+
+use filter::Audible;
+
+mod filter;
+mod iter;
+mod layout;
+mod notes;
+mod position;
+mod tempo;
+mod transpose;
+pub use filter::{Filter, TrackSelector};
+pub use iter::{MidiEventIter, TimedEvent};
+pub use layout::TrackLayout;
+pub use notes::ActiveNotes;
+pub use position::{ParsePositionError, Position};
+pub use transpose::{OutOfRange, Transpose};
+pub use tempo::TempoMap;
 
 pub struct MidiEventStream<'a> {
+    /// Armed for the deadline of the next event, and reused across polls.
+    /// Created on first poll, since creating a `Sleep` requires a runtime.
     sleep: Option<Pin<Box<Sleep>>>,
-    todo: &'a Smf<'a>,
-    // Ticks until next event, remaining events.
-    // Use .remove() when the iterator is empty
-    progress: Vec<(u32, Peekable<std::slice::Iter<'a, TrackEvent<'a>>>)>,
+    /// When the song started playing. Event deadlines are relative to this, not to the
+    /// previous event, so that lateness does not accumulate.
+    start: Option<Instant>,
+    /// Time in the song that `start` corresponds to. Nonzero after seeking.
+    offset: Duration,
+    /// Events to send right away, before any more from `events`.
+    /// Already filtered and transposed.
+    pending: VecDeque<TrackEventKind<'a>>,
+    /// Notes that have been sent and not stopped yet
+    active_notes: ActiveNotes,
+    /// Playback rate, where 1.0 is the speed of the file
+    speed: f64,
+    transpose: Transpose,
+    audible: Audible,
+    /// Start and end tick of the loop region, if looping
+    loop_region: Option<(u64, u64)>,
+    /// Set by `stop`. The stream ends once `pending` is empty.
+    stopped: bool,
+    events: MidiEventIter<'a>,
 }
 
 impl<'a> MidiEventStream<'a> {
     pub fn new(midi: &'a Smf<'a>) -> Self {
+        Self::with_layout(midi, TrackLayout::for_format(midi.header.format))
+    }
 
-        let mut progress = midi.tracks.iter().flat_map(
-            |track| {
-                let first = track.get(0)?;
-                let ticks: u32 = first.delta.into();
-                Some((ticks, track.iter().peekable()))
-            }
-        ).collect();
-
+    pub fn with_layout(midi: &'a Smf<'a>, layout: TrackLayout) -> Self {
         Self {
             sleep: None,
-            todo: midi,
-            progress,
+            start: None,
+            offset: Duration::ZERO,
+            pending: VecDeque::new(),
+            active_notes: ActiveNotes::new(),
+            speed: 1.0,
+            transpose: Transpose::default(),
+            audible: Filter::default().resolve(midi),
+            loop_region: None,
+            stopped: false,
+            events: MidiEventIter::with_layout(midi, layout),
+        }
+    }
+
+    pub fn tempo_map(&self) -> &TempoMap {
+        self.events.tempo_map()
+    }
+
+    pub fn events(&self) -> &MidiEventIter<'a> {
+        &self.events
+    }
+
+    /// Absolute tick of the given position, if it can be found in this file.
+    pub fn resolve(&self, position: &Position) -> Option<u64> {
+        self.events.resolve(position)
+    }
+
+    /// Jumps to the given absolute tick, and plays on from there as if the song started there.
+    ///
+    /// Notes that are still sounding are stopped. Then the state events that were skipped over
+    /// (see `MidiEventIter::seek`) are sent without waiting, so that instruments and controllers
+    /// are already set up.
+    pub fn seek(&mut self, tick: u64) {
+        self.jump(tick);
+        self.offset = self.tempo_map().tick_to_time(tick);
+        self.start = None;
+    }
+
+    /// Moves `events` to `tick`, and queues note-offs and chased state,
+    /// without changing the timing of the stream.
+    fn jump(&mut self, tick: u64) {
+        for event in self.pending.drain(..) {
+            self.active_notes.update(&event);
+        }
+        self.pending.extend(self.active_notes.note_offs());
+        for (_, _, track_index, event) in self.events.seek(tick) {
+            if let Some(event) = self.process(track_index, event) {
+                self.pending.push_back(event);
+            }
+        }
+    }
+
+    /// Plays at `speed` times the speed of the file, from now on.
+    ///
+    /// # Panics
+    /// If `speed` is not a positive, finite number.
+    pub fn set_speed(&mut self, speed: f64) {
+        assert!(speed.is_finite() && speed > 0.0, "speed must be positive, not {speed}");
+        // Keep the current position in the song where it is
+        if let Some(start) = self.start {
+            let now = Instant::now();
+            self.offset += (now - start).mul_f64(self.speed);
+            self.start = Some(now);
         }
+        self.speed = speed;
     }
 
-    fn ticks_to_time(&self, ticks: u32) -> Duration {
-        // TODO: handle tempo and tempo changes
-        Duration::from_millis(u64::from(ticks) * 1)
+    pub fn set_transpose(&mut self, transpose: Transpose) {
+        self.transpose = transpose;
+    }
+
+    /// Mutes or solos tracks and channels.
+    /// Changing this in the middle of a song can leave notes hanging.
+    pub fn set_filter(&mut self, filter: &Filter) {
+        self.audible = filter.resolve(self.events.midi());
+    }
+
+    /// Repeats the ticks from `start` up to (not including) `end` forever, or stops looping.
+    ///
+    /// At each wrap, sounding notes are stopped and the state at `start` is restored,
+    /// without any gap in timing. Controllers and pitch bend that change inside the loop are set
+    /// back to their value at `start`, or their default. Playback before `start` is unaffected.
+    ///
+    /// # Panics
+    /// If `start` is not before `end`.
+    pub fn set_loop(&mut self, region: Option<(u64, u64)>) {
+        if let Some((start, end)) = region {
+            assert!(start < end, "loop start {start} must be before loop end {end}");
+        }
+        self.loop_region = region;
+    }
+
+    /// Stops playback early. The stream then sends `ActiveNotes::cleanup` events
+    /// for the notes it left sounding, without waiting, and ends.
+    pub fn stop(&mut self) {
+        for event in self.pending.drain(..) {
+            self.active_notes.update(&event);
+        }
+        self.pending.extend(self.active_notes.cleanup());
+        self.stopped = true;
+    }
+
+    /// Filters and transposes an event about to be sent.
+    fn process(&self, track_index: usize, event: TrackEventKind<'a>) -> Option<TrackEventKind<'a>> {
+        if !self.audible.allows(track_index, &event) {
+            return None;
+        }
+        self.transpose.apply(event)
     }
 }
 
@@ -44,52 +172,374 @@ impl<'a> futures_core::stream::Stream for MidiEventStream<'a> {
         cx: &mut Context<'_>
     ) -> Poll<Option<Self::Item>> {
         let this = self.get_mut();
-        let progress = &mut this.progress;
-        // If there are no tracks left, return None
-        if progress.len() == 0 {
-            return Poll::Ready(None);
-        }
-
-        // If any event can be returned now, do so
-        for i in 0..progress.len() {
-            if i >= progress.len() { break; }
-            let (ticks, events) = &mut progress[i];
-            if *ticks == 0 {
-                // Return this event
-                let event = events.next().expect("empty track should have been removed already");
-
-                match events.peek() {
-                    Some(a) => {
-                        // Set the new event time
-                        *ticks = a.delta.into();
-                    },
-                    None => {
-                        // If this track has no more events, remove it
-                        progress.remove(i);
-                    },
+        loop {
+            let start = *this.start.get_or_insert_with(Instant::now);
+            if let Some(event) = this.pending.pop_front() {
+                this.active_notes.update(&event);
+                return Poll::Ready(Some(event));
+            }
+            if this.stopped {
+                return Poll::Ready(None);
+            }
+
+            // The next thing to happen is either the next event, or wrapping around the loop
+            let next_tick = this.events.peek_tick();
+            let wrap = match this.loop_region {
+                Some((loop_start, loop_end)) if next_tick.is_none_or(|tick| tick >= loop_end) => {
+                    Some((loop_start, loop_end))
+                }
+                _ => None,
+            };
+            // If there are no events left, return None
+            let Some(tick) = wrap.map(|(_, loop_end)| loop_end).or(next_tick) else {
+                return Poll::Ready(None);
+            };
+            let time = this.tempo_map().tick_to_time(tick);
+
+            // Wait until it is due. If the deadline already passed, this is ready immediately.
+            let deadline = start + time.saturating_sub(this.offset).div_f64(this.speed);
+            let sleep = this.sleep.get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+            if sleep.deadline() != deadline {
+                sleep.as_mut().reset(deadline);
+            }
+            ready!(sleep.as_mut().poll(cx));
+
+            if let Some((loop_start, loop_end)) = wrap {
+                // Carry on from the loop start exactly when the loop end was due
+                let defaults = this.events.defaults(loop_start, loop_end);
+                this.jump(loop_start);
+                // Controllers that changed inside the loop go back to their defaults first, so
+                // that a pedal held at the loop end does not hold the note-offs
+                for (_, _, track_index, event) in defaults.into_iter().rev() {
+                    if let Some(event) = this.process(track_index, event) {
+                        this.pending.push_front(event);
+                    }
                 }
-                return Poll::Ready(Some(event.kind))
+                this.offset = this.tempo_map().tick_to_time(loop_start);
+                this.start = Some(deadline);
+                continue;
+            }
+
+            let (_, _, track_index, event) = this.events.next().expect("peeked");
+            if let Some(event) = this.process(track_index, event) {
+                this.active_notes.update(&event);
+                return Poll::Ready(Some(event));
             }
         }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
 
-        // Else, find the lowest time, subtract it from all times, and sleep
-        let lowest_ticks = progress
-            .iter()
-            .map(|(ticks, _)| *ticks)
-            .min().expect("progress is not empty");
-        
-        for (ticks, _) in progress {
-            *ticks -= lowest_ticks;
+    use midly::{Format, Header, MidiMessage, Timing, TrackEvent, TrackEventKind};
+    use tokio_stream::StreamExt;
+
+    use super::*;
+
+    fn note_on(delta: u32, key: u8) -> TrackEvent<'static> {
+        TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Midi {
+                channel: 0.into(),
+                message: MidiMessage::NoteOn { key: key.into(), vel: 64.into() },
+            },
         }
+    }
 
-        let duration = this.ticks_to_time(lowest_ticks);
-        // dbg!(duration);
+    /// Two tracks at the default 120 BPM, with events at 0, 250, 500 and 1000 ms.
+    fn smf() -> Smf<'static> {
+        Smf {
+            header: Header::new(Format::Parallel, Timing::Metrical(480.into())),
+            tracks: vec![
+                vec![note_on(0, 60), note_on(480, 62)],
+                vec![note_on(240, 64), note_on(720, 65)],
+            ],
+        }
+    }
 
-        this.sleep = Some(Box::pin(tokio::time::sleep(duration)));
-        let sleep = this.sleep.as_mut().unwrap();
+    fn key(event: TrackEventKind) -> u8 {
+        match event {
+            TrackEventKind::Midi { message: MidiMessage::NoteOn { key, .. }, .. } => key.as_int(),
+            event => panic!("unexpected event {event:?}"),
+        }
+    }
 
-        ready!(sleep.as_mut().poll(cx));
+    #[tokio::test(start_paused = true)]
+    async fn events_in_order_and_on_time() {
+        let smf = smf();
+        let mut stream = MidiEventStream::new(&smf);
+        let start = Instant::now();
+        let mut events = vec![];
+        while let Some(event) = stream.next().await {
+            events.push((start.elapsed(), key(event)));
+        }
+        assert_eq!(events, [
+            (Duration::ZERO, 60),
+            (Duration::from_millis(250), 64),
+            (Duration::from_millis(500), 62),
+            (Duration::from_millis(1000), 65),
+        ]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn elapsed_deadlines_are_ready_immediately() {
+        let smf = smf();
+        let mut stream = MidiEventStream::new(&smf);
+        let start = Instant::now();
+        assert_eq!(stream.next().await.map(key), Some(60));
+        tokio::time::advance(Duration::from_millis(600)).await;
+        assert_eq!(stream.next().await.map(key), Some(64));
+        assert_eq!(stream.next().await.map(key), Some(62));
+        assert_eq!(start.elapsed(), Duration::from_millis(600));
+        assert_eq!(stream.next().await.map(key), Some(65));
+        assert_eq!(start.elapsed(), Duration::from_millis(1000));
+        assert_eq!(stream.next().await, None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn slow_consumer_does_not_drift() {
+        let smf = smf();
+        let mut stream = MidiEventStream::new(&smf);
+        let start = Instant::now();
+        let mut times = vec![];
+        while stream.next().await.is_some() {
+            times.push(start.elapsed());
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        assert_eq!(times, [0, 250, 500, 1000].map(Duration::from_millis));
+    }
+
+    #[test]
+    fn iter_merges_tracks() {
+        let smf = smf();
+        let events: Vec<_> = MidiEventIter::new(&smf)
+            .map(|(time, tick, track, kind)| (time, tick, track, key(kind)))
+            .collect();
+        assert_eq!(events, [
+            (Duration::ZERO, 0, 0, 60),
+            (Duration::from_millis(250), 240, 1, 64),
+            (Duration::from_millis(500), 480, 0, 62),
+            (Duration::from_millis(1000), 960, 1, 65),
+        ]);
+    }
 
-        todo!("handle case where sleep finished before polling (probably by putting this whole function in a loop {{}})")
+    #[test]
+    fn sequential_patterns_play_in_order() {
+        let mut smf = smf();
+        smf.header.format = Format::Sequential;
+        let keys = |layout| -> Vec<_> {
+            MidiEventIter::with_layout(&smf, layout)
+                .map(|(time, tick, _, kind)| (time, tick, key(kind)))
+                .collect()
+        };
+        assert_eq!(keys(TrackLayout::for_format(smf.header.format)), [
+            (Duration::ZERO, 0, 60),
+            (Duration::from_millis(500), 480, 62),
+            (Duration::from_millis(750), 720, 64),
+            (Duration::from_millis(1500), 1440, 65),
+        ]);
+        assert_eq!(keys(TrackLayout::Single(1)), [
+            (Duration::from_millis(250), 240, 64),
+            (Duration::from_millis(1000), 960, 65),
+        ]);
     }
-}
\ No newline at end of file
+
+    #[tokio::test(start_paused = true)]
+    async fn seek_chases_state() {
+        let event = |delta: u32, message| TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Midi { channel: 1.into(), message },
+        };
+        let mut smf = smf();
+        smf.tracks.push(vec![
+            event(0, MidiMessage::ProgramChange { program: 5.into() }),
+            event(0, MidiMessage::Controller { controller: 7.into(), value: 100.into() }),
+            event(0, MidiMessage::ProgramChange { program: 6.into() }),
+            event(100, MidiMessage::Controller { controller: 7.into(), value: 90.into() }),
+            event(0, MidiMessage::NoteOn { key: 70.into(), vel: 64.into() }),
+        ]);
+        let mut stream = MidiEventStream::new(&smf);
+        stream.seek(stream.resolve(&Position::Time(Duration::from_millis(400))).unwrap());
+        let start = Instant::now();
+        let mut events = vec![];
+        while let Some(event) = stream.next().await {
+            events.push((start.elapsed(), event));
+        }
+        let midi = |channel: u8, message| TrackEventKind::Midi { channel: channel.into(), message };
+        let note_on = |key: u8| midi(0, MidiMessage::NoteOn { key: key.into(), vel: 64.into() });
+        assert_eq!(events, [
+            (Duration::ZERO, midi(1, MidiMessage::ProgramChange { program: 6.into() })),
+            (Duration::ZERO, midi(1, MidiMessage::Controller { controller: 7.into(), value: 90.into() })),
+            (Duration::from_millis(100), note_on(62)),
+            (Duration::from_millis(600), note_on(65)),
+        ]);
+    }
+
+    #[test]
+    fn seek_chases_parameters() {
+        let cc = |channel: u8, controller: u8, value: u8| TrackEventKind::Midi {
+            channel: channel.into(),
+            message: MidiMessage::Controller { controller: controller.into(), value: value.into() },
+        };
+        let mut smf = smf();
+        // Pitch bend range of 12 semitones, then the null RPN so that later data entry does nothing
+        smf.tracks.push([(101, 0), (100, 0), (6, 12), (101, 127), (100, 127), (6, 1)]
+            .map(|(controller, value)| TrackEvent { delta: 0.into(), kind: cc(1, controller, value) })
+            .to_vec());
+        let mut events = MidiEventIter::new(&smf);
+        let chased: Vec<_> = events.seek(480).into_iter().map(|(_, _, _, kind)| kind).collect();
+        assert_eq!(chased, [cc(1, 101, 0), cc(1, 100, 0), cc(1, 6, 12), cc(1, 101, 127), cc(1, 100, 127)]);
+
+        // Reset All Controllers leaves volume alone, and is chased itself
+        smf.tracks.push([(7, 40), (11, 50), (64, 127), (121, 0)]
+            .map(|(controller, value)| TrackEvent { delta: 0.into(), kind: cc(2, controller, value) })
+            .to_vec());
+        let mut events = MidiEventIter::new(&smf);
+        let chased: Vec<_> = events.seek(480).into_iter().map(|(_, _, _, kind)| kind).collect();
+        assert_eq!(chased[5..], [cc(2, 7, 40), cc(2, 121, 0)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn speed_and_transpose() {
+        let smf = smf();
+        let mut stream = MidiEventStream::new(&smf);
+        stream.set_speed(2.0);
+        stream.set_transpose(Transpose::new(64, OutOfRange::Drop));
+        let start = Instant::now();
+        let mut events = vec![];
+        while let Some(event) = stream.next().await {
+            events.push((start.elapsed(), key(event)));
+        }
+        assert_eq!(events, [(Duration::ZERO, 124), (Duration::from_millis(250), 126)]);
+
+        let note = |channel: u8, key: u8| TrackEventKind::Midi {
+            channel: channel.into(),
+            message: MidiMessage::NoteOn { key: key.into(), vel: 64.into() },
+        };
+        // Clamped notes play at the ends of the range instead
+        assert_eq!(Transpose::new(64, OutOfRange::Clamp).apply(note(0, 70)), Some(note(0, 127)));
+        assert_eq!(Transpose::new(-64, OutOfRange::Clamp).apply(note(0, 10)), Some(note(0, 0)));
+        // Keys on the percussion channel are drums, so they stay where they are
+        assert_eq!(Transpose::new(64, OutOfRange::Drop).apply(note(9, 70)), Some(note(9, 70)));
+        assert_eq!(Transpose::new(-5, OutOfRange::Clamp).apply(note(9, 36)), Some(note(9, 36)));
+    }
+
+    #[test]
+    fn filter_tracks_and_channels() {
+        let mut smf = smf();
+        smf.tracks[1].insert(0, TrackEvent {
+            delta: 0.into(),
+            kind: TrackEventKind::Meta(midly::MetaMessage::TrackName(b"Bass ")),
+        });
+        let audible = |filter: Filter| -> Vec<_> {
+            let audible = filter.resolve(&smf);
+            MidiEventIter::new(&smf)
+                .filter(|(_, _, track, kind)| audible.allows(*track, kind))
+                .filter_map(|(_, _, _, kind)| kind.as_live_event().map(|_| key(kind)))
+                .collect()
+        };
+        let bass = || vec!["bass".parse().unwrap()];
+        assert_eq!(audible(Filter { soloed_tracks: bass(), ..Filter::default() }), [64, 65]);
+        assert_eq!(audible(Filter { muted_tracks: bass(), ..Filter::default() }), [60, 62]);
+        assert_eq!(audible(Filter { muted_channels: vec![0], ..Filter::default() }), [0u8; 0]);
+        assert_eq!(audible(Filter { soloed_channels: vec![0], ..Filter::default() }), [60, 64, 62, 65]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn loop_markers() {
+        let marker = |delta: u32, text| TrackEvent { delta: delta.into(), kind: TrackEventKind::Meta(midly::MetaMessage::Marker(text)) };
+        let note_off = |delta: u32, key: u8| TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Midi { channel: 0.into(), message: MidiMessage::NoteOff { key: key.into(), vel: 0.into() } },
+        };
+        let smf = Smf {
+            header: Header::new(Format::SingleTrack, Timing::Metrical(480.into())),
+            tracks: vec![vec![
+                note_on(0, 60),
+                marker(480, b"loopStart"),
+                note_on(0, 62),
+                note_off(240, 60),
+                marker(240, b"loop_end"),
+                note_off(0, 62),
+            ]],
+        };
+        let mut stream = MidiEventStream::new(&smf);
+        stream.set_loop(stream.events().loop_markers());
+        let start = Instant::now();
+        let mut events = vec![];
+        while events.len() < 10 {
+            if let TrackEventKind::Midi { message, .. } = stream.next().await.unwrap() {
+                events.push((start.elapsed().as_millis(), message));
+            }
+        }
+        let on = |key: u8| MidiMessage::NoteOn { key: key.into(), vel: 64.into() };
+        let off = |key: u8| MidiMessage::NoteOff { key: key.into(), vel: 0.into() };
+        assert_eq!(events, [
+            (0, on(60)),
+            (500, on(62)),
+            (750, off(60)),
+            // Wrap: 62 is still sounding
+            (1000, off(62)),
+            (1000, on(62)),
+            (1250, off(60)),
+            (1500, off(62)),
+            (1500, on(62)),
+            (1750, off(60)),
+            (2000, off(62)),
+        ]);
+
+        // The sustain pedal is pressed inside the loop, and let go of after it
+        let pedal = |delta: u32, value: u8| TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Midi { channel: 0.into(), message: MidiMessage::Controller { controller: 64.into(), value: value.into() } },
+        };
+        let smf = Smf {
+            header: Header::new(Format::SingleTrack, Timing::Metrical(480.into())),
+            tracks: vec![vec![
+                marker(480, b"loopStart"),
+                pedal(0, 127),
+                note_on(0, 62),
+                marker(480, b"loopEnd"),
+                note_off(0, 62),
+                pedal(0, 0),
+            ]],
+        };
+        let mut stream = MidiEventStream::new(&smf);
+        stream.set_loop(stream.events().loop_markers());
+        let start = Instant::now();
+        let mut events = vec![];
+        while events.len() < 8 {
+            if let TrackEventKind::Midi { message, .. } = stream.next().await.unwrap() {
+                events.push((start.elapsed().as_millis(), message));
+            }
+        }
+        let sustain = |value: u8| MidiMessage::Controller { controller: 64.into(), value: value.into() };
+        assert_eq!(events, [
+            (500, sustain(127)),
+            (500, on(62)),
+            // Wrap: the pedal is let go of before the note-off, so that it is not held
+            (1000, sustain(0)),
+            (1000, off(62)),
+            (1000, sustain(127)),
+            (1000, on(62)),
+            (1500, sustain(0)),
+            (1500, off(62)),
+        ]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stop_cleans_up() {
+        let smf = smf();
+        let mut stream = MidiEventStream::new(&smf);
+        assert_eq!(stream.next().await.map(key), Some(60));
+        stream.stop();
+        let events: Vec<_> = stream.collect().await;
+        assert_eq!(events.len(), 1 + 2 * 16);
+        assert_eq!(events[0], TrackEventKind::Midi {
+            channel: 0.into(),
+            message: MidiMessage::NoteOff { key: 60.into(), vel: 0.into() },
+        });
+    }
+}
@@ -1,34 +1,183 @@
 use std::{io::{self, prelude::*}, fs::OpenOptions};
-use midly::TrackEventKind;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio_stream::StreamExt;
 
-#[tokio::main]
-async fn main() {
-    let filename = match std::env::args().nth(1) {
-        Some(filename) => filename,
-        None => {
-            eprintln!("Usage: {} [filename]", std::env::args().nth(0).as_deref().unwrap_or("cargo run"));
-            std::process::exit(1);
+struct Options {
+    filename: String,
+    /// Play only this track (pattern, for Format 2 files)
+    pattern: Option<usize>,
+    /// Where to start playing from
+    start: Option<parser::Position>,
+    speed: f64,
+    transpose: parser::Transpose,
+    filter: parser::Filter,
+    looping: Option<Loop>,
+}
+
+enum Loop {
+    Between(parser::Position, parser::Position),
+    /// Between "loopStart" and "loopEnd" markers
+    Markers,
+}
+
+fn usage() -> ! {
+    eprintln!(
+        "Usage: {} [--pattern N] [--start TIME|bar:N|tick:N] [--speed X] [--transpose SEMITONES [--clamp]] \
+         [--mute-track INDEX|NAME]... [--solo-track INDEX|NAME]... [--mute-channel 1-16]... [--solo-channel 1-16]... \
+         [--loop START END | --loop-markers] [filename]\n\
+         Positions are a time ([[H:]M:]S[.fff]), bar:N, tick:N or marker:NAME",
+        std::env::args().next().as_deref().unwrap_or("cargo run"),
+    );
+    std::process::exit(1);
+}
+
+fn parse_args() -> Options {
+    let mut filename = None;
+    let mut pattern = None;
+    let mut start = None;
+    let mut speed = 1.0;
+    let mut transpose = parser::Transpose::default();
+    let mut filter = parser::Filter::default();
+    let mut looping = None;
+    let position = |value: Option<String>| -> parser::Position {
+        value.unwrap_or_else(|| usage()).parse().unwrap_or_else(|err| {
+            eprintln!("Invalid position: {err}");
+            usage()
+        })
+    };
+    // Channels are numbered from 1 on the command line, like on instruments and in sequencers
+    let channel = |value: Option<String>| -> u8 {
+        match value.as_deref().map(str::parse) {
+            Some(Ok(channel @ 1..=16)) => channel - 1,
+            _ => usage(),
         }
     };
 
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pattern" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                pattern = Some(value.parse().unwrap_or_else(|_| usage()));
+            }
+            "--start" => start = Some(position(args.next())),
+            "--speed" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                speed = value.parse().ok().filter(|speed: &f64| speed.is_finite() && *speed > 0.0).unwrap_or_else(|| usage());
+            }
+            "--transpose" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                transpose.semitones = value.parse().unwrap_or_else(|_| usage());
+            }
+            "--clamp" => transpose.out_of_range = parser::OutOfRange::Clamp,
+            "--mute-track" => {
+                let Ok(track) = args.next().unwrap_or_else(|| usage()).parse();
+                filter.muted_tracks.push(track);
+            }
+            "--solo-track" => {
+                let Ok(track) = args.next().unwrap_or_else(|| usage()).parse();
+                filter.soloed_tracks.push(track);
+            }
+            "--mute-channel" => filter.muted_channels.push(channel(args.next())),
+            "--solo-channel" => filter.soloed_channels.push(channel(args.next())),
+            "--loop" => looping = Some(Loop::Between(position(args.next()), position(args.next()))),
+            "--loop-markers" => looping = Some(Loop::Markers),
+            _ if filename.is_none() => filename = Some(arg),
+            _ => usage(),
+        }
+    }
+
+    Options {
+        filename: filename.unwrap_or_else(|| usage()),
+        pattern,
+        start,
+        speed,
+        transpose,
+        filter,
+        looping,
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let options = parse_args();
+
     let mut stdout = OpenOptions::new().write(true).open("/dev/stdout").unwrap();
 
-    let mut file = std::fs::File::open(&filename).expect("Failed to open file");
+    let mut file = std::fs::File::open(&options.filename).expect("Failed to open file");
     let file_length = file.seek(io::SeekFrom::End(0));
-    file.rewind();
+    file.rewind().expect("Failed to read file");
 
     let mut data = Vec::with_capacity(file_length.unwrap_or(0).try_into().unwrap_or(0));
 
-    file.read_to_end(&mut data);
+    file.read_to_end(&mut data).expect("Failed to read file");
 
     let smf = midly::Smf::parse(&data).unwrap();
 
-    let mut midi_stream = parser::MidiEventStream::new(&smf);
+    let layout = match options.pattern {
+        Some(pattern) if pattern >= smf.tracks.len() => {
+            eprintln!("Pattern {pattern} does not exist, file has {} tracks", smf.tracks.len());
+            std::process::exit(1);
+        }
+        Some(pattern) => parser::TrackLayout::Single(pattern),
+        None => parser::TrackLayout::for_format(smf.header.format),
+    };
+
+    let mut midi_stream = parser::MidiEventStream::with_layout(&smf, layout);
+    midi_stream.set_speed(options.speed);
+    midi_stream.set_transpose(options.transpose);
+    midi_stream.set_filter(&options.filter);
+
+    let resolve = |position: &parser::Position| midi_stream.resolve(position).unwrap_or_else(|| {
+        eprintln!("Cannot find {position:?} in this file");
+        std::process::exit(1);
+    });
+
+    let start = options.start.as_ref().map(resolve);
+    let loop_region = match &options.looping {
+        Some(Loop::Between(start, end)) => match (resolve(start), resolve(end)) {
+            (start, end) if start < end => Some((start, end)),
+            _ => {
+                eprintln!("Loop start must be before loop end");
+                std::process::exit(1);
+            }
+        },
+        Some(Loop::Markers) => match midi_stream.events().loop_markers() {
+            Some(region) => Some(region),
+            None => {
+                eprintln!("No loopStart/loopEnd markers in this file");
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    if let Some(start) = start {
+        midi_stream.seek(start);
+    }
+    midi_stream.set_loop(loop_region);
 
     // tokio::pin!(midi_stream);
 
-    while let Some(msg) = midi_stream.next().await {
+    // Stop cleanly, without leaving notes hanging in the player
+    let mut interrupt = signal(SignalKind::interrupt()).expect("Failed to handle SIGINT");
+    let mut terminate = signal(SignalKind::terminate()).expect("Failed to handle SIGTERM");
+
+    loop {
+        let msg = tokio::select! {
+            msg = midi_stream.next() => msg,
+            _ = interrupt.recv() => {
+                midi_stream.stop();
+                continue;
+            }
+            _ = terminate.recv() => {
+                midi_stream.stop();
+                continue;
+            }
+        };
+        let Some(msg) = msg else {
+            break;
+        };
         // match msg {
         //     TrackEventKind::Midi { channel, message } => match message {
         //         midly::MidiMessage::NoteOff { key, vel } => todo!(),
@@ -44,7 +193,11 @@ async fn main() {
         //     }
         // }
         if let Some(msg) = msg.as_live_event() {
-            msg.write_std(&mut stdout).unwrap();
+            if let Err(err) = msg.write_std(&mut stdout) {
+                // Most likely the player went away, so there is nobody left to clean up for
+                eprintln!("Failed to write MIDI data: {err}");
+                std::process::exit(1);
+            }
         }
     }
 
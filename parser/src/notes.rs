@@ -0,0 +1,63 @@
+use midly::{MidiMessage, TrackEventKind};
+
+/// Keeps track of which notes are sounding on each channel, from the events that were sent.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ActiveNotes {
+    /// One bit per key, per channel
+    notes: [u128; 16],
+}
+
+impl ActiveNotes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a note starting or stopping.
+    pub fn update(&mut self, event: &TrackEventKind) {
+        let TrackEventKind::Midi { channel, message } = event else {
+            return;
+        };
+        let notes = &mut self.notes[usize::from(channel.as_int())];
+        match message {
+            MidiMessage::NoteOn { key, vel } if vel.as_int() > 0 => *notes |= 1 << key.as_int(),
+            MidiMessage::NoteOn { key, .. } | MidiMessage::NoteOff { key, .. } => *notes &= !(1 << key.as_int()),
+            // All Sound Off, All Notes Off, and the mode changes that imply All Notes Off
+            MidiMessage::Controller { controller, .. } if matches!(controller.as_int(), 120 | 123..=127) => *notes = 0,
+            _ => {}
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notes.iter().all(|&notes| notes == 0)
+    }
+
+    /// Note-offs for every sounding note, which are then no longer sounding.
+    pub fn note_offs(&mut self) -> Vec<TrackEventKind<'static>> {
+        let mut events = vec![];
+        for (channel, notes) in self.notes.iter_mut().enumerate() {
+            for key in (0..128u8).filter(|key| *notes & (1 << key) != 0) {
+                events.push(TrackEventKind::Midi {
+                    channel: (channel as u8).into(),
+                    message: MidiMessage::NoteOff { key: key.into(), vel: 0.into() },
+                });
+            }
+            *notes = 0;
+        }
+        events
+    }
+
+    /// Note-offs for every sounding note, then All Notes Off and All Sound Off on every channel,
+    /// for when playback stops early and nothing may be left ringing.
+    pub fn cleanup(&mut self) -> Vec<TrackEventKind<'static>> {
+        let mut events = self.note_offs();
+        for channel in 0..16u8 {
+            for controller in [123u8, 120] {
+                events.push(TrackEventKind::Midi {
+                    channel: channel.into(),
+                    message: MidiMessage::Controller { controller: controller.into(), value: 0.into() },
+                });
+            }
+        }
+        events
+    }
+}
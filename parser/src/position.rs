@@ -0,0 +1,73 @@
+use std::{fmt, str::FromStr, time::Duration};
+
+/// A point in a song, resolved to a tick with `MidiEventIter::resolve`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Position {
+    /// Time since the start of the song
+    Time(Duration),
+    /// Absolute tick
+    Tick(u64),
+    /// Start of a bar, counting from 1
+    Bar(u32),
+    /// The first `MetaMessage::Marker` with this text
+    Marker(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePositionError;
+
+impl fmt::Display for ParsePositionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected a time ([[H:]M:]S[.fff]), bar:N, tick:N or marker:NAME")
+    }
+}
+
+impl std::error::Error for ParsePositionError {}
+
+impl FromStr for Position {
+    type Err = ParsePositionError;
+
+    /// Parses `bar:17`, `tick:1920`, `marker:Chorus`, or a time such as `90`, `1:30.5` or `1:02:03`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(marker) = s.strip_prefix("marker:") {
+            return Ok(Position::Marker(marker.to_owned()));
+        }
+        if let Some(bar) = s.strip_prefix("bar:") {
+            return bar.parse().map(Position::Bar).map_err(|_| ParsePositionError);
+        }
+        if let Some(tick) = s.strip_prefix("tick:") {
+            return tick.parse().map(Position::Tick).map_err(|_| ParsePositionError);
+        }
+
+        let mut parts = s.rsplit(':');
+        let seconds: f64 = parts.next().and_then(|seconds| seconds.parse().ok()).ok_or(ParsePositionError)?;
+        let mut total = seconds;
+        for (part, scale) in parts.zip([60.0, 3600.0]) {
+            let value: u32 = part.parse().map_err(|_| ParsePositionError)?;
+            total += f64::from(value) * scale;
+        }
+        if s.split(':').count() > 3 {
+            return Err(ParsePositionError);
+        }
+        Duration::try_from_secs_f64(total).map(Position::Time).map_err(|_| ParsePositionError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let time = |seconds: f64| Ok(Position::Time(Duration::from_secs_f64(seconds)));
+        assert_eq!("90".parse(), time(90.0));
+        assert_eq!("1:30.5".parse(), time(90.5));
+        assert_eq!("1:02:03".parse(), time(3723.0));
+        assert_eq!("bar:17".parse(), Ok(Position::Bar(17)));
+        assert_eq!("tick:1920".parse(), Ok(Position::Tick(1920)));
+        assert_eq!("marker:Chorus".parse(), Ok(Position::Marker("Chorus".to_owned())));
+        for invalid in ["1:2:3:4", "-5", "nan", "bar:x", ""] {
+            assert_eq!(invalid.parse::<Position>(), Err(ParsePositionError), "{invalid}");
+        }
+    }
+}
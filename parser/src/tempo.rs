@@ -0,0 +1,249 @@
+use std::time::Duration;
+
+use midly::{Fps, MetaMessage, Smf, Timing, TrackEventKind};
+
+use crate::TrackLayout;
+
+/// Tempo used until the first `MetaMessage::Tempo` event, as specified by SMF (120 BPM).
+const DEFAULT_MICROS_PER_BEAT: u32 = 500_000;
+
+#[derive(Debug, Clone, Copy)]
+struct TempoChange {
+    /// Absolute tick at which this tempo takes effect
+    tick: u64,
+    micros_per_beat: u32,
+    /// Time from the start of the song to `tick`
+    time: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Resolution {
+    /// Ticks per beat. The length of a beat is given by tempo events.
+    Metrical(u16),
+    /// `ticks` ticks per `seconds` seconds, regardless of tempo events.
+    /// A fraction, so that 29.97 fps (`30000 / 1001`) files stay frame-accurate.
+    Timecode { ticks: u64, seconds: u64 },
+}
+
+impl Resolution {
+    fn new(timing: Timing) -> Self {
+        match timing {
+            Timing::Metrical(ticks_per_beat) => Resolution::Metrical(ticks_per_beat.as_int()),
+            Timing::Timecode(fps, subframes) => {
+                let (frames, seconds) = match fps {
+                    Fps::Fps24 => (24, 1),
+                    Fps::Fps25 => (25, 1),
+                    // Drop-frame only affects how frames are numbered;
+                    // the actual rate is 30 / 1.001 frames per second.
+                    Fps::Fps29 => (30_000, 1001),
+                    Fps::Fps30 => (30, 1),
+                };
+                Resolution::Timecode { ticks: frames * u64::from(subframes), seconds }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MeterChange {
+    /// Absolute tick at which this time signature takes effect. Always starts a new bar.
+    tick: u64,
+    numerator: u8,
+    /// Power of two, as in `MetaMessage::TimeSignature`
+    denominator: u8,
+}
+
+/// Converts absolute tick positions in a file to time since the start of the song,
+/// following every tempo change in every track.
+#[derive(Debug, Clone)]
+pub struct TempoMap {
+    resolution: Resolution,
+    /// Sorted by tick. Always contains at least one entry, at tick 0.
+    /// Timecode-based files only ever have that one entry.
+    changes: Vec<TempoChange>,
+    /// Sorted by tick. Always contains at least one entry, at tick 0 (4/4 by default).
+    meters: Vec<MeterChange>,
+}
+
+impl TempoMap {
+    pub fn new(midi: &Smf) -> Self {
+        Self::with_layout(midi, TrackLayout::for_format(midi.header.format))
+    }
+
+    /// Builds the tempo map from only the tracks that play in `layout`, placed where they play.
+    pub fn with_layout(midi: &Smf, layout: TrackLayout) -> Self {
+        let resolution = Resolution::new(midi.header.timing);
+
+        let mut metas: Vec<(u64, MetaMessage)> = layout.offsets(midi).into_iter().flat_map(|(track_index, offset)| {
+            midi.tracks[track_index].iter().scan(offset, |tick, event| {
+                *tick += u64::from(u32::from(event.delta));
+                Some((*tick, event.kind))
+            }).filter_map(|(tick, kind)| match kind {
+                TrackEventKind::Meta(meta @ (MetaMessage::Tempo(..) | MetaMessage::TimeSignature(..))) => {
+                    Some((tick, meta))
+                }
+                _ => None,
+            })
+        }).collect();
+        // Stable, so that same-tick changes keep track order and the last one wins.
+        metas.sort_by_key(|&(tick, _)| tick);
+
+        let mut meters = vec![MeterChange { tick: 0, numerator: 4, denominator: 2 }];
+        for &(tick, meta) in &metas {
+            if let MetaMessage::TimeSignature(numerator, denominator, ..) = meta {
+                let last = meters.last_mut().expect("meters is not empty");
+                let change = MeterChange { tick, numerator: numerator.max(1), denominator };
+                if last.tick == tick {
+                    *last = change;
+                } else {
+                    meters.push(change);
+                }
+            }
+        }
+
+        let tempos = metas.into_iter().filter_map(|(tick, meta)| match (meta, resolution) {
+            (MetaMessage::Tempo(tempo), Resolution::Metrical(_)) => Some((tick, tempo.as_int())),
+            _ => None,
+        });
+
+        let mut changes = vec![TempoChange {
+            tick: 0,
+            micros_per_beat: DEFAULT_MICROS_PER_BEAT,
+            time: Duration::ZERO,
+        }];
+        for (tick, micros_per_beat) in tempos {
+            let last = changes.last_mut().expect("changes is not empty");
+            if last.tick == tick {
+                last.micros_per_beat = micros_per_beat;
+                continue;
+            }
+            let time = last.time + Self::span(resolution, last.micros_per_beat, tick - last.tick);
+            changes.push(TempoChange { tick, micros_per_beat, time });
+        }
+
+        Self { resolution, changes, meters }
+    }
+
+    fn span(resolution: Resolution, micros_per_beat: u32, ticks: u64) -> Duration {
+        let nanos = match resolution {
+            // A malformed header with zero ticks per beat would otherwise divide by zero.
+            Resolution::Metrical(0) => u128::from(ticks) * 1_000_000,
+            Resolution::Metrical(ticks_per_beat) => {
+                u128::from(ticks) * u128::from(micros_per_beat) * 1000 / u128::from(ticks_per_beat)
+            }
+            Resolution::Timecode { ticks: 0, .. } => u128::from(ticks) * 1_000_000,
+            Resolution::Timecode { ticks: ticks_per, seconds } => {
+                u128::from(ticks) * u128::from(seconds) * 1_000_000_000 / u128::from(ticks_per)
+            }
+        };
+        Duration::from_nanos(nanos.try_into().unwrap_or(u64::MAX))
+    }
+
+    /// Time from the start of the song to the given absolute tick.
+    pub fn tick_to_time(&self, tick: u64) -> Duration {
+        let index = self.changes.partition_point(|change| change.tick <= tick) - 1;
+        let change = &self.changes[index];
+        change.time + Self::span(self.resolution, change.micros_per_beat, tick - change.tick)
+    }
+
+    /// The first absolute tick at or after the given time from the start of the song.
+    pub fn time_to_tick(&self, time: Duration) -> u64 {
+        let index = self.changes.partition_point(|change| change.time <= time) - 1;
+        let change = &self.changes[index];
+        let nanos = (time - change.time).as_nanos();
+        let (numerator, denominator) = match self.resolution {
+            Resolution::Metrical(0) | Resolution::Timecode { ticks: 0, .. } => (1, 1_000_000),
+            Resolution::Metrical(ticks_per_beat) => {
+                (u128::from(ticks_per_beat), u128::from(change.micros_per_beat) * 1000)
+            }
+            Resolution::Timecode { ticks, seconds } => (u128::from(ticks), u128::from(seconds) * 1_000_000_000),
+        };
+        let ticks = (nanos * numerator).div_ceil(denominator.max(1));
+        change.tick + u64::try_from(ticks).unwrap_or(u64::MAX - change.tick)
+    }
+
+    /// Absolute tick at which the given bar (counting from 1) starts.
+    ///
+    /// `None` for timecode-based files, which have no beats to count bars with.
+    pub fn bar_to_tick(&self, bar: u32) -> Option<u64> {
+        let Resolution::Metrical(ticks_per_beat) = self.resolution else {
+            return None;
+        };
+        let bar_length = |meter: &MeterChange| {
+            // Four quarter notes per whole note
+            let whole_note = u64::from(meter.numerator) * u64::from(ticks_per_beat) * 4;
+            whole_note.checked_shr(u32::from(meter.denominator)).unwrap_or(0).max(1)
+        };
+
+        let mut first_bar = 1u64;
+        let target = u64::from(bar.max(1));
+        for (meter, next) in self.meters.iter().zip(self.meters.iter().skip(1)) {
+            let length = bar_length(meter);
+            // A time signature change in the middle of a bar starts a new bar.
+            let bars = (next.tick - meter.tick).div_ceil(length);
+            if target < first_bar + bars {
+                return Some(meter.tick + (target - first_bar) * length);
+            }
+            first_bar += bars;
+        }
+        let meter = self.meters.last().expect("meters is not empty");
+        Some(meter.tick + (target - first_bar) * bar_length(meter))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use midly::{Format, Header, TrackEvent};
+
+    #[test]
+    fn tempo_change() {
+        let tempo = |delta: u32, micros: u32| TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Meta(MetaMessage::Tempo(micros.into())),
+        };
+        let smf = Smf {
+            header: Header::new(Format::Parallel, Timing::Metrical(480.into())),
+            tracks: vec![vec![tempo(0, 1_000_000), tempo(960, 250_000)]],
+        };
+        let map = TempoMap::new(&smf);
+        assert_eq!(map.tick_to_time(480), Duration::from_secs(1));
+        assert_eq!(map.tick_to_time(960), Duration::from_secs(2));
+        assert_eq!(map.tick_to_time(1440), Duration::from_millis(2250));
+    }
+
+    #[test]
+    fn timecode() {
+        let tempo = |delta: u32| TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Meta(MetaMessage::Tempo(1.into())),
+        };
+        let smf = Smf {
+            header: Header::new(Format::Parallel, Timing::Timecode(Fps::Fps29, 80)),
+            tracks: vec![vec![tempo(0)]],
+        };
+        let map = TempoMap::new(&smf);
+        // Tempo events do not apply to timecode-based files.
+        assert_eq!(map.tick_to_time(80), Duration::from_nanos(1_000_000_000 * 1001 / 30_000));
+        assert_eq!(map.tick_to_time(30 * 60 * 60 * 80), Duration::from_secs_f64(3600.0 * 1.001));
+    }
+
+    #[test]
+    fn positions() {
+        let event = |delta: u32, meta| TrackEvent { delta: delta.into(), kind: TrackEventKind::Meta(meta) };
+        let smf = Smf {
+            header: Header::new(Format::Parallel, Timing::Metrical(480.into())),
+            tracks: vec![vec![
+                event(0, MetaMessage::TimeSignature(3, 2, 24, 8)),
+                event(960, MetaMessage::Tempo(1_000_000.into())),
+                event(480, MetaMessage::TimeSignature(6, 3, 24, 8)),
+            ]],
+        };
+        let map = TempoMap::new(&smf);
+        assert_eq!(map.time_to_tick(Duration::from_millis(500)), 480);
+        assert_eq!(map.time_to_tick(Duration::from_millis(1500)), 1200);
+        assert_eq!(map.bar_to_tick(1), Some(0));
+        assert_eq!(map.bar_to_tick(2), Some(1440));
+        assert_eq!(map.bar_to_tick(4), Some(1440 + 2 * 1440));
+    }
+}
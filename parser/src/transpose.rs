@@ -0,0 +1,52 @@
+use midly::{MidiMessage, TrackEventKind};
+
+/// Channel 10, which General MIDI reserves for percussion, where keys are drums and not pitches.
+const PERCUSSION_CHANNEL: u8 = 9;
+
+/// What to do with notes that would be transposed outside of 0..=127.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutOfRange {
+    /// Leave them out
+    #[default]
+    Drop,
+    /// Play them at 0 or 127 instead
+    Clamp,
+}
+
+/// Shifts notes by a number of semitones, leaving the percussion channel alone.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Transpose {
+    pub semitones: i8,
+    pub out_of_range: OutOfRange,
+}
+
+impl Transpose {
+    pub fn new(semitones: i8, out_of_range: OutOfRange) -> Self {
+        Self { semitones, out_of_range }
+    }
+
+    /// The transposed event, or `None` if it should be dropped.
+    pub fn apply<'a>(&self, event: TrackEventKind<'a>) -> Option<TrackEventKind<'a>> {
+        let TrackEventKind::Midi { channel, message } = event else {
+            return Some(event);
+        };
+        if self.semitones == 0 || channel.as_int() == PERCUSSION_CHANNEL {
+            return Some(event);
+        }
+        let transpose = |key: midly::num::u7| {
+            let key = i16::from(key.as_int()) + i16::from(self.semitones);
+            match (u8::try_from(key), self.out_of_range) {
+                (Ok(key @ 0..=127), _) => Some(key.into()),
+                (_, OutOfRange::Drop) => None,
+                (_, OutOfRange::Clamp) => Some((key.clamp(0, 127) as u8).into()),
+            }
+        };
+        let message = match message {
+            MidiMessage::NoteOff { key, vel } => MidiMessage::NoteOff { key: transpose(key)?, vel },
+            MidiMessage::NoteOn { key, vel } => MidiMessage::NoteOn { key: transpose(key)?, vel },
+            MidiMessage::Aftertouch { key, vel } => MidiMessage::Aftertouch { key: transpose(key)?, vel },
+            message => message,
+        };
+        Some(TrackEventKind::Midi { channel, message })
+    }
+}
@@ -1,2 +1,2 @@
 #!/bin/bash
-cargo b --release; cargo r --release --bin parser "$1" | cargo r --release --bin player
+cargo b --release; cargo r --release --bin player "$1"
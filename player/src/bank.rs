@@ -0,0 +1,258 @@
+//! Patch banks, which choose the instrument for each General MIDI program.
+//!
+//! Patches can be loaded from a TOML file, so that they can be tweaked without recompiling:
+//!
+//! ```toml
+//! [[patch]]
+//! name = "Bright piano"   # Optional, for error messages
+//! programs = [0, 1]       # Optional, defaults to the patch's position in the file
+//! amplitudes = [1.0, 1.0, 0.3, 0.2, 0.2]
+//!
+//! ratios = [1.0, 2.01]    # Optional, as are the fields below
+//! damping = 0.5
+//! noise = 0.0
+//! vibrato = [5.0, 0.1]
+//! tremolo = [0.0, 0.0]
+//!
+//! [patch.envelope]        # Optional, as is every field in it. Times are in seconds.
+//! attack = 0.001
+//! decay = 0.5
+//! sustain = 0.5
+//! release = 0.1
+//! ```
+//!
+//! `amplitudes` are those of the fundamental and each harmonic above it. `ratios` moves partials
+//! away from the harmonics, giving each one's frequency relative to the note, and partials it does
+//! not cover stay harmonic. `damping` is the time constant in seconds with which each partial
+//! fades relative to the one below it. `noise` adds noise around the note's pitch. `vibrato` is
+//! a rate in Hz and a depth in semitones, and `tremolo` a rate in Hz and a depth from 0.0 to 1.0.
+//! Programs that no patch is given for keep their built-in instrument.
+//!
+//! Presets from a soundfont take the place of these instruments, for the programs they cover.
+
+use std::{borrow::Cow, fmt, io, path::Path, sync::Arc};
+
+use serde::Deserialize;
+
+use super::{instrument, soundfont::{Preset, SoundFont}, Envelope, Instrument};
+
+const PROGRAM_COUNT: usize = 128;
+
+/// The instrument for each of the 128 General MIDI programs
+#[derive(Debug, Clone)]
+pub struct Bank {
+    programs: [Arc<Instrument>; PROGRAM_COUNT],
+    /// Sampled presets, which play instead of `programs` where there are any
+    presets: [Option<Arc<Preset>>; PROGRAM_COUNT],
+    /// Sampled percussion for each program on channel 10, which plays instead of the built-in
+    /// drums where there is any
+    drum_kits: [Option<Arc<Preset>>; PROGRAM_COUNT],
+}
+
+#[derive(Debug)]
+pub enum BankError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    /// A patch that parsed, but cannot be played
+    Invalid { patch: String, reason: &'static str },
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BankError::Io(err) => write!(f, "cannot read patch file: {err}"),
+            BankError::Parse(err) => write!(f, "cannot parse patch file: {err}"),
+            BankError::Invalid { patch, reason } => write!(f, "patch {patch}: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for BankError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            BankError::Io(err) => Some(err),
+            BankError::Parse(err) => Some(err),
+            BankError::Invalid { .. } => None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PatchFile {
+    #[serde(default)]
+    patch: Vec<Patch>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Patch {
+    name: Option<String>,
+    programs: Option<Vec<usize>>,
+    amplitudes: Vec<f64>,
+    #[serde(default)]
+    ratios: Vec<f64>,
+    #[serde(default)]
+    damping: f64,
+    #[serde(default)]
+    noise: f64,
+    #[serde(default)]
+    vibrato: (f64, f64),
+    #[serde(default)]
+    tremolo: (f64, f64),
+    #[serde(default)]
+    envelope: Envelope,
+}
+
+impl Bank {
+    /// The instruments compiled into the player
+    pub fn builtin() -> Self {
+        Bank {
+            programs: std::array::from_fn(|program| Arc::new(instrument::INSTRUMENTS[program].clone())),
+            presets: [const { None }; PROGRAM_COUNT],
+            drum_kits: [const { None }; PROGRAM_COUNT],
+        }
+    }
+
+    /// Patches from a TOML patch file, on top of the built-in instruments.
+    pub fn parse(toml: &str) -> Result<Self, BankError> {
+        let file: PatchFile = toml::from_str(toml).map_err(BankError::Parse)?;
+        let mut bank = Bank::builtin();
+        for (index, patch) in file.patch.into_iter().enumerate() {
+            let invalid = |reason| BankError::Invalid {
+                patch: patch.name.clone().unwrap_or_else(|| format!("#{}", index + 1)),
+                reason,
+            };
+            if patch.amplitudes.is_empty() || !patch.amplitudes.iter().all(|amp| amp.is_finite()) {
+                return Err(invalid("amplitudes must be a list of numbers"));
+            }
+            let Envelope { attack, decay, sustain, release } = patch.envelope;
+            if ![attack, decay, release].iter().all(|time| time.is_finite() && *time >= 0.0) {
+                return Err(invalid("envelope times must not be negative"));
+            }
+            if !(0.0..=1.0).contains(&sustain) {
+                return Err(invalid("envelope sustain must be from 0.0 to 1.0"));
+            }
+            if !patch.ratios.iter().all(|ratio| ratio.is_finite() && *ratio > 0.0) {
+                return Err(invalid("ratios must be positive"));
+            }
+            let (vibrato_rate, vibrato_depth) = patch.vibrato;
+            let (tremolo_rate, tremolo_depth) = patch.tremolo;
+            if ![patch.damping, patch.noise, vibrato_rate, vibrato_depth, tremolo_rate].iter().all(|value| value.is_finite() && *value >= 0.0) {
+                return Err(invalid("damping, noise, vibrato and tremolo must not be negative"));
+            }
+            if !(0.0..=1.0).contains(&tremolo_depth) {
+                return Err(invalid("tremolo depth must be from 0.0 to 1.0"));
+            }
+            let programs = patch.programs.clone().unwrap_or_else(|| vec![index]);
+            if programs.iter().any(|program| *program >= PROGRAM_COUNT) {
+                return Err(invalid("programs must be from 0 to 127"));
+            }
+
+            let instrument = Arc::new(Instrument {
+                full_amplitude: patch.amplitudes.iter().sum(),
+                amplitudes: Cow::Owned(patch.amplitudes),
+                ratios: Cow::Owned(patch.ratios),
+                envelope: patch.envelope,
+                damping: patch.damping,
+                noise: patch.noise,
+                vibrato: patch.vibrato,
+                tremolo: patch.tremolo,
+            });
+            for program in programs {
+                bank.programs[program] = Arc::clone(&instrument);
+            }
+        }
+        Ok(bank)
+    }
+
+    /// Reads a TOML patch file, as described for `parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, BankError> {
+        Bank::parse(&std::fs::read_to_string(path).map_err(BankError::Io)?)
+    }
+
+    /// Plays the soundfont's presets in bank 0 in place of instruments, and its percussion presets
+    /// in place of the built-in drums. Programs that the soundfont has no preset for keep their
+    /// instrument.
+    ///
+    /// Channel 10 chooses a drum kit by its program, falling back to program 0. If the soundfont
+    /// has no kit for program 0, its first kit stands in for it.
+    pub fn with_soundfont(mut self, soundfont: SoundFont) -> Self {
+        let (presets, drum_kits) = soundfont.into_presets();
+        for (program, preset) in presets {
+            self.presets[usize::from(program)].get_or_insert_with(|| Arc::new(preset));
+        }
+        let drum_kits: Vec<_> = drum_kits.into_iter().map(|(program, preset)| (program, Arc::new(preset))).collect();
+        for (program, preset) in &drum_kits {
+            self.drum_kits[usize::from(*program)].get_or_insert_with(|| Arc::clone(preset));
+        }
+        if let (None, Some((_, preset))) = (&self.drum_kits[0], drum_kits.first()) {
+            self.drum_kits[0] = Some(Arc::clone(preset));
+        }
+        self
+    }
+
+    pub(super) fn program(&self, program: u8) -> &Arc<Instrument> {
+        &self.programs[usize::from(program) % PROGRAM_COUNT]
+    }
+
+    pub(super) fn preset(&self, program: u8) -> Option<&Arc<Preset>> {
+        self.presets[usize::from(program) % PROGRAM_COUNT].as_ref()
+    }
+
+    /// The drum kit for channel 10 when it is set to `program`
+    pub(super) fn drum_kit(&self, program: u8) -> Option<&Arc<Preset>> {
+        self.drum_kits[usize::from(program) % PROGRAM_COUNT].as_ref().or(self.drum_kits[0].as_ref())
+    }
+}
+
+impl Default for Bank {
+    fn default() -> Self {
+        Bank::builtin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patches() {
+        let bank = Bank::parse(r#"
+            [[patch]]
+            amplitudes = [1.0, 0.5]
+            [patch.envelope]
+            release = 0.25
+
+            [[patch]]
+            name = "Organ"
+            programs = [16, 17]
+            amplitudes = [1.0, 0.0, 0.5]
+            ratios = [1.0, 2.5]
+            vibrato = [6.0, 0.1]
+        "#).unwrap();
+
+        // Position in the file, with the rest of the envelope left at its defaults
+        assert_eq!(bank.program(0).amplitudes[..], [1.0, 0.5]);
+        assert_eq!(bank.program(0).envelope.release, 0.25);
+        assert_eq!(bank.program(0).envelope.decay, Envelope::DEFAULT_ENVELOPE.decay);
+        assert!(Arc::ptr_eq(bank.program(16), bank.program(17)));
+        assert_eq!(bank.program(16).amplitudes[..], [1.0, 0.0, 0.5]);
+        assert_eq!(bank.program(16).ratios[..], [1.0, 2.5]);
+        assert_eq!(bank.program(16).vibrato, (6.0, 0.1));
+        assert_eq!(bank.program(16).damping, 0.0);
+        // Everything else is built in
+        assert!(std::ptr::eq(&*bank.program(1).amplitudes, &*instrument::INSTRUMENTS[1].amplitudes));
+    }
+
+    #[test]
+    fn invalid_patches() {
+        let error = |toml| Bank::parse(toml).unwrap_err().to_string();
+        assert_eq!(error("[[patch]]\nname = \"Bad\"\nprograms = [128]\namplitudes = [1.0]"), "patch Bad: programs must be from 0 to 127");
+        assert_eq!(error("[[patch]]\namplitudes = []"), "patch #1: amplitudes must be a list of numbers");
+        assert_eq!(error("[[patch]]\namplitudes = [1.0]\nenvelope = { sustain = 2.0 }"), "patch #1: envelope sustain must be from 0.0 to 1.0");
+        assert_eq!(error("[[patch]]\namplitudes = [1.0]\nratios = [0.0]"), "patch #1: ratios must be positive");
+        assert_eq!(error("[[patch]]\namplitudes = [1.0]\ntremolo = [4.0, 1.5]"), "patch #1: tremolo depth must be from 0.0 to 1.0");
+        assert!(matches!(Bank::parse("[[patch]]\namplitude = [1.0]"), Err(BankError::Parse(_))));
+    }
+}
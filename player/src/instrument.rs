@@ -1,54 +1,80 @@
+use std::borrow::Cow;
+
 use super::{Instrument, Envelope};
 
+/// Defines an instrument from its amplitudes and envelope, followed by any other fields that
+/// differ from `Instrument::PLAIN`.
 macro_rules! make_instrument {
-    ($name:ident: [$($amps:expr),*], $envelope:expr) => {
+    ($name:ident: [$($amps:expr),*], $envelope:expr $(, $field:ident: $value:expr)* $(,)?) => {
         const $name: Instrument = Instrument {
-            amplitudes: &[$($amps),*],
+            amplitudes: Cow::Borrowed(&[$($amps),*]),
             full_amplitude: $($amps +)* 0.0,
             envelope: $envelope,
+            $($field: $value,)*
+            ..Instrument::PLAIN
         };
     };
 }
 
 impl Envelope {
     pub const DEFAULT_ENVELOPE: Envelope = Envelope {
-        attack: 44,
-        decay: 22050, // 0.5s
+        attack: 0.001,
+        decay: 0.5,
         sustain: 0.5,
-        release: 4410, // 0.1s
+        release: 0.1,
     };
 }
 
+/// An envelope with times in seconds, to keep the instruments below short.
+const fn envelope(attack: f64, decay: f64, sustain: f64, release: f64) -> Envelope {
+    Envelope { attack, decay, sustain, release }
+}
+
 impl Instrument {
+    /// A sine wave with the default envelope, for instruments to build on
+    const PLAIN: Instrument = Instrument {
+        amplitudes: Cow::Borrowed(&[1.0]),
+        ratios: Cow::Borrowed(&[]),
+        full_amplitude: 1.0,
+        envelope: Envelope::DEFAULT_ENVELOPE,
+        damping: 0.0,
+        noise: 0.0,
+        vibrato: (0.0, 0.0),
+        tremolo: (0.0, 0.0),
+    };
+
     make_instrument!{
         VIOLIN:
         [1.0, 0.6, 0.6, 0.7, 0.4, 0.2, 0.4, 0.1],
         Envelope {
-            attack: 1,
-            decay: 17640,
+            attack: 0.0,
+            decay: 0.4,
             sustain: 0.8,
-            release: 4410,
+            release: 0.1,
         }
     }
     make_instrument!{
         PIANO:
         [1.0, 1.0, 0.1, 0.2, 0.2],
-        Envelope::DEFAULT_ENVELOPE
+        Envelope::DEFAULT_ENVELOPE,
+        damping: 0.8
     }
     make_instrument!{
         GUITAR:
         [1.0, 0.7, 0.3, 0.4, 0.4, 0.2, 0.4, 0.1],
         Envelope {
-            attack: 1,
-            decay: 44100,
+            attack: 0.0,
+            decay: 1.0,
             sustain: 0.2,
-            release: 4410,
+            release: 0.1,
         }
     }
     make_instrument!{
         FLUTE:
         [1.0, 1.0, 0.1, 0.2, 0.2],
-        Envelope::DEFAULT_ENVELOPE
+        Envelope::DEFAULT_ENVELOPE,
+        noise: 0.3,
+        vibrato: (5.0, 0.05)
     }
     make_instrument!{
         RECORDER:
@@ -59,10 +85,10 @@ impl Instrument {
         PIZZICATO_STRINGS:
         [1.0, 0.7, 0.3, 0.4, 0.4, 0.2, 0.4, 0.1],
         Envelope {
-            attack: 1,
-            decay: 17640, // 0.4s
+            attack: 0.0,
+            decay: 0.4,
             sustain: 0.0,
-            release: 1,
+            release: 0.0,
         }
     }
     make_instrument!{
@@ -76,176 +102,1067 @@ impl Instrument {
         Envelope::DEFAULT_ENVELOPE
     }
 
+    // Bright Acoustic Piano
     make_instrument!{
-        TODO:
-        [1.0, 1.0, 0.1, 0.2, 0.2],
-        Envelope {
-            attack: 44,
-            decay: 22050,
-            sustain: 0.5,
-            release: 4410,
-        }
+        BRIGHT_PIANO:
+        [1.0, 0.9, 0.6, 0.5, 0.4, 0.3, 0.2, 0.15],
+        envelope(0.002, 1.2, 0.2, 0.3),
+        damping: 0.5
+    }
+    // Electric Grand Piano
+    make_instrument!{
+        ELECTRIC_GRAND:
+        [1.0, 0.7, 0.5, 0.3, 0.25, 0.1],
+        envelope(0.002, 1.0, 0.3, 0.25),
+        damping: 0.3
+    }
+    // Honky-tonk Piano
+    make_instrument!{
+        HONKY_TONK:
+        [0.8, 0.8, 0.4, 0.4, 0.2, 0.2],
+        envelope(0.002, 1.0, 0.2, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 1.007, 2.0, 2.014, 3.0, 3.021]),
+        damping: 0.5
+    }
+    // Electric Piano 1
+    make_instrument!{
+        ELECTRIC_PIANO:
+        [1.0, 0.35, 0.1, 0.15],
+        envelope(0.002, 1.8, 0.15, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 3.0, 7.0]),
+        damping: 0.15
+    }
+    // Electric Piano 2
+    make_instrument!{
+        FM_PIANO:
+        [1.0, 0.5, 0.3, 0.25, 0.2],
+        envelope(0.001, 1.2, 0.2, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 3.0, 5.0, 9.0]),
+        damping: 0.1
+    }
+    // Harpsichord
+    make_instrument!{
+        HARPSICHORD:
+        [1.0, 0.9, 0.8, 0.7, 0.6, 0.5, 0.45, 0.4, 0.35, 0.3],
+        envelope(0.001, 0.8, 0.0, 0.1),
+        damping: 0.6
+    }
+    // Clavinet
+    make_instrument!{
+        CLAVINET:
+        [0.6, 1.0, 0.8, 0.7, 0.5, 0.4, 0.3, 0.2],
+        envelope(0.001, 0.6, 0.1, 0.05),
+        damping: 0.3
+    }
+    // Celesta
+    make_instrument!{
+        CELESTA:
+        [1.0, 0.3, 0.1],
+        envelope(0.001, 1.0, 0.0, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 4.0]),
+        damping: 0.2
+    }
+    // Glockenspiel
+    make_instrument!{
+        GLOCKENSPIEL:
+        [1.0, 0.5, 0.25, 0.1],
+        envelope(0.001, 1.5, 0.0, 0.5),
+        ratios: Cow::Borrowed(&[1.0, 2.76, 5.4, 8.93]),
+        damping: 0.15
+    }
+    // Music Box
+    make_instrument!{
+        MUSIC_BOX:
+        [1.0, 0.4, 0.2, 0.08],
+        envelope(0.001, 1.2, 0.0, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 3.9, 6.1]),
+        damping: 0.2
+    }
+    // Vibraphone
+    make_instrument!{
+        VIBRAPHONE:
+        [1.0, 0.2, 0.05],
+        envelope(0.001, 2.5, 0.0, 0.6),
+        ratios: Cow::Borrowed(&[1.0, 4.0, 10.0]),
+        damping: 0.3,
+        tremolo: (5.5, 0.3)
+    }
+    // Marimba
+    make_instrument!{
+        MARIMBA:
+        [1.0, 0.3, 0.05],
+        envelope(0.001, 0.5, 0.0, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 4.0, 9.9]),
+        damping: 0.05
+    }
+    // Xylophone
+    make_instrument!{
+        XYLOPHONE:
+        [1.0, 0.5, 0.2],
+        envelope(0.001, 0.3, 0.0, 0.05),
+        ratios: Cow::Borrowed(&[1.0, 3.0, 6.0]),
+        damping: 0.05,
+        noise: 0.3
+    }
+    // Tubular Bells
+    make_instrument!{
+        TUBULAR_BELLS:
+        [0.5, 1.0, 0.6, 0.4, 0.3, 0.2],
+        envelope(0.001, 4.0, 0.0, 1.0),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 3.0, 4.2, 5.4, 6.8]),
+        damping: 1.0
+    }
+    // Dulcimer
+    make_instrument!{
+        DULCIMER:
+        [1.0, 0.8, 0.6, 0.5, 0.4, 0.3],
+        envelope(0.001, 1.5, 0.0, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.003, 3.0, 4.005, 5.0, 6.008]),
+        damping: 0.3
+    }
+    // Drawbar Organ
+    make_instrument!{
+        DRAWBAR_ORGAN:
+        [1.0, 1.0, 0.0, 0.8, 0.0, 0.5, 0.0, 0.6],
+        envelope(0.005, 0.01, 1.0, 0.05)
+    }
+    // Percussive Organ
+    make_instrument!{
+        PERCUSSIVE_ORGAN:
+        [1.0, 0.8, 1.0, 0.5],
+        envelope(0.002, 0.3, 0.7, 0.05),
+        damping: 0.2
+    }
+    // Rock Organ
+    make_instrument!{
+        ROCK_ORGAN:
+        [1.0, 0.9, 0.7, 0.7, 0.5, 0.5, 0.3, 0.4],
+        envelope(0.003, 0.01, 1.0, 0.05),
+        vibrato: (6.5, 0.08),
+        tremolo: (6.5, 0.2)
+    }
+    // Church Organ
+    make_instrument!{
+        CHURCH_ORGAN:
+        [1.0, 0.7, 0.5, 0.6, 0.3, 0.4, 0.2, 0.3, 0.15, 0.2],
+        envelope(0.08, 0.1, 1.0, 0.4),
+        noise: 0.1
+    }
+    // Reed Organ
+    make_instrument!{
+        REED_ORGAN:
+        [1.0, 0.6, 0.5, 0.4, 0.35, 0.3, 0.25, 0.2],
+        envelope(0.05, 0.1, 0.9, 0.15),
+        noise: 0.2
+    }
+    // Accordion
+    make_instrument!{
+        ACCORDION:
+        [0.8, 0.8, 0.6, 0.5, 0.4, 0.3],
+        envelope(0.03, 0.1, 0.9, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 1.004, 2.0, 3.0, 4.0, 5.0])
+    }
+    // Harmonica
+    make_instrument!{
+        HARMONICA:
+        [1.0, 0.8, 0.7, 0.3, 0.5, 0.2],
+        envelope(0.04, 0.1, 0.8, 0.1),
+        noise: 0.4,
+        vibrato: (5.0, 0.1)
+    }
+    // Tango Accordion
+    make_instrument!{
+        TANGO_ACCORDION:
+        [0.7, 0.5, 0.5, 0.6, 0.4, 0.3],
+        envelope(0.03, 0.1, 0.9, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 0.996, 1.004, 2.0, 3.0, 4.0])
+    }
+    // Acoustic Guitar (steel)
+    make_instrument!{
+        STEEL_GUITAR:
+        [1.0, 0.8, 0.6, 0.5, 0.45, 0.35, 0.3, 0.2, 0.15],
+        envelope(0.001, 1.5, 0.0, 0.2),
+        damping: 0.4
+    }
+    // Electric Guitar (jazz)
+    make_instrument!{
+        JAZZ_GUITAR:
+        [1.0, 0.5, 0.2, 0.1],
+        envelope(0.002, 1.5, 0.2, 0.2),
+        damping: 0.3
+    }
+    // Electric Guitar (clean)
+    make_instrument!{
+        CLEAN_GUITAR:
+        [1.0, 0.6, 0.4, 0.3, 0.2, 0.1],
+        envelope(0.001, 2.0, 0.2, 0.15),
+        damping: 0.6
+    }
+    // Electric Guitar (muted)
+    make_instrument!{
+        MUTED_GUITAR:
+        [1.0, 0.5, 0.3, 0.1],
+        envelope(0.001, 0.15, 0.0, 0.05),
+        damping: 0.05
+    }
+    // Overdriven Guitar
+    make_instrument!{
+        OVERDRIVEN_GUITAR:
+        [1.0, 0.8, 0.7, 0.6, 0.55, 0.5, 0.45, 0.4, 0.35, 0.3, 0.25, 0.2],
+        envelope(0.002, 2.0, 0.6, 0.15),
+        damping: 1.5
+    }
+    // Distortion Guitar
+    make_instrument!{
+        DISTORTION_GUITAR:
+        [1.0, 0.7, 0.8, 0.7, 0.6, 0.4, 0.5, 0.4],
+        envelope(0.002, 2.0, 0.7, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 1.5, 2.0, 3.0, 4.0, 4.5, 5.0, 6.0]),
+        noise: 0.2
+    }
+    // Guitar Harmonics
+    make_instrument!{
+        GUITAR_HARMONICS:
+        [1.0, 0.2],
+        envelope(0.001, 2.0, 0.0, 0.3),
+        ratios: Cow::Borrowed(&[2.0, 4.0])
+    }
+    // Acoustic Bass
+    make_instrument!{
+        ACOUSTIC_BASS:
+        [1.0, 0.5, 0.2, 0.1],
+        envelope(0.002, 1.5, 0.1, 0.15),
+        damping: 0.2
+    }
+    // Electric Bass (finger)
+    make_instrument!{
+        FINGER_BASS:
+        [1.0, 0.6, 0.3, 0.2, 0.1],
+        envelope(0.002, 2.0, 0.2, 0.1),
+        damping: 0.3
+    }
+    // Electric Bass (pick)
+    make_instrument!{
+        PICK_BASS:
+        [1.0, 0.7, 0.5, 0.4, 0.3, 0.2],
+        envelope(0.001, 1.5, 0.2, 0.1),
+        damping: 0.2,
+        noise: 0.2
+    }
+    // Fretless Bass
+    make_instrument!{
+        FRETLESS_BASS:
+        [1.0, 0.5, 0.25, 0.1],
+        envelope(0.02, 1.0, 0.5, 0.15),
+        vibrato: (4.5, 0.08)
+    }
+    // Slap Bass 1
+    make_instrument!{
+        SLAP_BASS:
+        [1.0, 0.8, 0.7, 0.6, 0.5, 0.4, 0.3],
+        envelope(0.001, 0.8, 0.1, 0.1),
+        damping: 0.05,
+        noise: 0.5
+    }
+    // Slap Bass 2
+    make_instrument!{
+        SLAP_BASS_2:
+        [1.0, 0.9, 0.5, 0.7, 0.3, 0.5],
+        envelope(0.001, 0.6, 0.1, 0.1),
+        damping: 0.08,
+        noise: 0.3
+    }
+    // Synth Bass 1
+    make_instrument!{
+        SYNTH_BASS:
+        [1.0, 0.5, 0.333, 0.25, 0.2, 0.167, 0.143, 0.125],
+        envelope(0.002, 0.4, 0.5, 0.1),
+        damping: 0.3
+    }
+    // Synth Bass 2
+    make_instrument!{
+        SYNTH_BASS_2:
+        [1.0, 0.0, 0.333, 0.0, 0.2, 0.0, 0.143],
+        envelope(0.005, 0.6, 0.6, 0.1),
+        damping: 0.5
+    }
+    // Viola
+    make_instrument!{
+        VIOLA:
+        [1.0, 0.7, 0.7, 0.5, 0.4, 0.3, 0.3, 0.1],
+        envelope(0.05, 0.4, 0.8, 0.15),
+        noise: 0.1,
+        vibrato: (5.5, 0.1)
+    }
+    // Cello
+    make_instrument!{
+        CELLO:
+        [1.0, 0.8, 0.5, 0.5, 0.35, 0.25, 0.2, 0.1],
+        envelope(0.06, 0.4, 0.8, 0.2),
+        noise: 0.1,
+        vibrato: (5.0, 0.1)
+    }
+    // Contrabass
+    make_instrument!{
+        CONTRABASS:
+        [1.0, 0.7, 0.4, 0.3, 0.2],
+        envelope(0.08, 0.4, 0.8, 0.2),
+        noise: 0.1,
+        vibrato: (4.5, 0.08)
+    }
+    // Tremolo Strings
+    make_instrument!{
+        TREMOLO_STRINGS:
+        [1.0, 0.6, 0.6, 0.7, 0.4, 0.2, 0.4, 0.1],
+        envelope(0.02, 0.3, 0.8, 0.15),
+        noise: 0.1,
+        tremolo: (12.0, 0.6)
+    }
+    // Orchestral Harp
+    make_instrument!{
+        HARP:
+        [1.0, 0.4, 0.2, 0.1, 0.05],
+        envelope(0.002, 2.0, 0.0, 0.4),
+        damping: 0.5
+    }
+    // Timpani
+    make_instrument!{
+        TIMPANI:
+        [1.0, 0.6, 0.4, 0.2],
+        envelope(0.002, 1.5, 0.0, 0.4),
+        ratios: Cow::Borrowed(&[1.0, 1.5, 1.99, 2.44]),
+        damping: 0.3,
+        noise: 0.5
+    }
+    // String Ensemble 1
+    make_instrument!{
+        STRING_ENSEMBLE:
+        [0.6, 0.5, 0.5, 0.6, 0.4, 0.3, 0.2],
+        envelope(0.15, 0.3, 0.9, 0.4),
+        ratios: Cow::Borrowed(&[1.0, 1.003, 0.997, 2.0, 3.0, 4.0, 5.0]),
+        vibrato: (5.0, 0.05)
+    }
+    // String Ensemble 2
+    make_instrument!{
+        SLOW_STRINGS:
+        [0.6, 0.5, 0.5, 0.4, 0.3, 0.2],
+        envelope(0.4, 0.3, 0.9, 0.6),
+        ratios: Cow::Borrowed(&[1.0, 1.004, 0.996, 2.0, 3.0, 4.0]),
+        vibrato: (4.5, 0.05)
+    }
+    // Synth Strings 1
+    make_instrument!{
+        SYNTH_STRINGS:
+        [0.6, 0.6, 0.4, 0.3, 0.3, 0.2],
+        envelope(0.1, 0.3, 0.8, 0.4),
+        ratios: Cow::Borrowed(&[1.0, 1.005, 2.0, 2.01, 3.0, 4.0])
+    }
+    // Synth Strings 2
+    make_instrument!{
+        SYNTH_STRINGS_2:
+        [0.6, 0.6, 0.5, 0.33, 0.25, 0.2],
+        envelope(0.3, 0.4, 0.8, 0.5),
+        ratios: Cow::Borrowed(&[1.0, 0.995, 2.0, 3.0, 4.0, 5.0])
+    }
+    // Choir Aahs
+    make_instrument!{
+        CHOIR:
+        [1.0, 0.5, 0.3, 0.6, 0.8, 0.3, 0.1, 0.05],
+        envelope(0.15, 0.3, 0.9, 0.4),
+        noise: 0.1,
+        vibrato: (5.0, 0.12)
+    }
+    // Voice Oohs
+    make_instrument!{
+        VOICE_OOHS:
+        [1.0, 0.6, 0.15, 0.05],
+        envelope(0.12, 0.3, 0.9, 0.3),
+        vibrato: (5.0, 0.1)
+    }
+    // Synth Voice
+    make_instrument!{
+        SYNTH_VOICE:
+        [1.0, 0.4, 0.5, 0.2, 0.3],
+        envelope(0.08, 0.3, 0.8, 0.3),
+        vibrato: (5.5, 0.08),
+        tremolo: (5.5, 0.1)
+    }
+    // Orchestra Hit
+    make_instrument!{
+        ORCHESTRA_HIT:
+        [1.0, 0.8, 0.8, 0.5, 0.6, 0.5, 0.4, 0.3],
+        envelope(0.002, 0.4, 0.0, 0.15),
+        ratios: Cow::Borrowed(&[1.0, 1.5, 2.0, 2.5, 3.0, 4.0, 5.0, 6.0]),
+        damping: 0.2,
+        noise: 0.8
+    }
+    // Trumpet
+    make_instrument!{
+        TRUMPET:
+        [1.0, 0.9, 0.8, 0.7, 0.6, 0.5, 0.4, 0.3, 0.2, 0.1],
+        envelope(0.03, 0.2, 0.8, 0.1),
+        noise: 0.05,
+        vibrato: (5.5, 0.05)
+    }
+    // Trombone
+    make_instrument!{
+        TROMBONE:
+        [1.0, 0.8, 0.6, 0.5, 0.4, 0.3, 0.2],
+        envelope(0.05, 0.2, 0.8, 0.15)
+    }
+    // Tuba
+    make_instrument!{
+        TUBA:
+        [1.0, 0.6, 0.3, 0.15],
+        envelope(0.05, 0.2, 0.8, 0.15)
+    }
+    // Muted Trumpet
+    make_instrument!{
+        MUTED_TRUMPET:
+        [0.3, 0.5, 0.8, 1.0, 0.6, 0.3, 0.1],
+        envelope(0.03, 0.2, 0.7, 0.1)
+    }
+    // French Horn
+    make_instrument!{
+        FRENCH_HORN:
+        [1.0, 0.5, 0.25, 0.12, 0.05],
+        envelope(0.08, 0.2, 0.85, 0.2)
+    }
+    // Brass Section
+    make_instrument!{
+        BRASS_SECTION:
+        [0.8, 0.6, 0.8, 0.7, 0.5, 0.4, 0.3],
+        envelope(0.05, 0.2, 0.8, 0.15),
+        ratios: Cow::Borrowed(&[1.0, 1.004, 2.0, 3.0, 4.0, 5.0, 6.0])
+    }
+    // Synth Brass 1
+    make_instrument!{
+        SYNTH_BRASS:
+        [1.0, 0.5, 0.333, 0.25, 0.2, 0.167],
+        envelope(0.02, 0.3, 0.7, 0.15),
+        damping: 1.0
+    }
+    // Synth Brass 2
+    make_instrument!{
+        SYNTH_BRASS_2:
+        [1.0, 0.6, 0.45, 0.3, 0.25],
+        envelope(0.06, 0.4, 0.7, 0.2),
+        ratios: Cow::Borrowed(&[1.0, 1.006, 2.0, 3.0, 4.0])
+    }
+    // Soprano Sax
+    make_instrument!{
+        SOPRANO_SAX:
+        [1.0, 0.7, 0.6, 0.4, 0.3, 0.2],
+        envelope(0.03, 0.2, 0.8, 0.1),
+        noise: 0.2,
+        vibrato: (5.0, 0.06)
+    }
+    // Alto Sax
+    make_instrument!{
+        ALTO_SAX:
+        [1.0, 0.8, 0.7, 0.6, 0.4, 0.3, 0.2],
+        envelope(0.03, 0.2, 0.8, 0.1),
+        noise: 0.25,
+        vibrato: (5.0, 0.06)
+    }
+    // Tenor Sax
+    make_instrument!{
+        TENOR_SAX:
+        [1.0, 0.9, 0.8, 0.6, 0.5, 0.4, 0.3, 0.2],
+        envelope(0.03, 0.2, 0.8, 0.1),
+        noise: 0.3,
+        vibrato: (4.5, 0.06)
+    }
+    // Baritone Sax
+    make_instrument!{
+        BARITONE_SAX:
+        [1.0, 1.0, 0.7, 0.6, 0.5, 0.4, 0.3, 0.3],
+        envelope(0.04, 0.2, 0.8, 0.1),
+        noise: 0.3
+    }
+    // Oboe
+    make_instrument!{
+        OBOE:
+        [0.5, 1.0, 0.8, 0.6, 0.4, 0.3, 0.2],
+        envelope(0.03, 0.1, 0.9, 0.1),
+        vibrato: (5.0, 0.04)
+    }
+    // English Horn
+    make_instrument!{
+        ENGLISH_HORN:
+        [0.7, 1.0, 0.6, 0.4, 0.3, 0.15],
+        envelope(0.04, 0.1, 0.9, 0.12),
+        vibrato: (4.5, 0.04)
+    }
+    // Bassoon
+    make_instrument!{
+        BASSOON:
+        [0.6, 0.8, 1.0, 0.7, 0.5, 0.3],
+        envelope(0.04, 0.1, 0.9, 0.12)
+    }
+    // Clarinet
+    make_instrument!{
+        CLARINET:
+        [1.0, 0.05, 0.6, 0.05, 0.4, 0.05, 0.25, 0.03, 0.1],
+        envelope(0.03, 0.1, 0.9, 0.1)
+    }
+    // Piccolo
+    make_instrument!{
+        PICCOLO:
+        [1.0, 0.3, 0.05],
+        envelope(0.03, 0.1, 0.9, 0.08),
+        noise: 0.3,
+        vibrato: (5.5, 0.05)
+    }
+    // Pan Flute
+    make_instrument!{
+        PAN_FLUTE:
+        [1.0, 0.15, 0.05],
+        envelope(0.05, 0.1, 0.8, 0.15),
+        noise: 0.8
+    }
+    // Blown Bottle
+    make_instrument!{
+        BLOWN_BOTTLE:
+        [1.0, 0.05],
+        envelope(0.08, 0.1, 0.8, 0.15),
+        noise: 1.2
+    }
+    // Shakuhachi
+    make_instrument!{
+        SHAKUHACHI:
+        [1.0, 0.4, 0.1],
+        envelope(0.08, 0.2, 0.8, 0.2),
+        noise: 0.9,
+        vibrato: (4.0, 0.1)
+    }
+    // Whistle
+    make_instrument!{
+        WHISTLE:
+        [1.0, 0.05],
+        envelope(0.03, 0.1, 0.9, 0.1),
+        noise: 0.1,
+        vibrato: (6.0, 0.1)
+    }
+    // Ocarina
+    make_instrument!{
+        OCARINA:
+        [1.0, 0.1, 0.02],
+        envelope(0.03, 0.1, 0.9, 0.1),
+        noise: 0.2
+    }
+    // Lead 3 (calliope)
+    make_instrument!{
+        CALLIOPE:
+        [1.0, 0.4, 0.2],
+        envelope(0.02, 0.1, 0.9, 0.1),
+        noise: 0.6,
+        vibrato: (6.0, 0.08)
+    }
+    // Lead 4 (chiff)
+    make_instrument!{
+        CHIFF:
+        [1.0, 0.5, 0.3, 0.2],
+        envelope(0.01, 0.1, 0.8, 0.1),
+        damping: 0.05,
+        noise: 0.5
+    }
+    // Lead 5 (charang)
+    make_instrument!{
+        CHARANG:
+        [1.0, 0.9, 0.8, 0.7, 0.6, 0.5, 0.5, 0.4, 0.4, 0.3],
+        envelope(0.005, 0.5, 0.6, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 3.003, 4.0, 5.005, 6.0, 7.007, 8.0, 9.009, 10.0])
+    }
+    // Lead 6 (voice)
+    make_instrument!{
+        VOICE_LEAD:
+        [1.0, 0.5, 0.6, 0.3, 0.2],
+        envelope(0.05, 0.2, 0.8, 0.2),
+        vibrato: (5.5, 0.15)
+    }
+    // Lead 7 (fifths)
+    make_instrument!{
+        FIFTHS_LEAD:
+        [1.0, 0.7, 0.5, 0.3, 0.2],
+        envelope(0.01, 0.2, 0.8, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 1.5, 2.0, 3.0, 4.5])
+    }
+    // Lead 8 (bass + lead)
+    make_instrument!{
+        BASS_LEAD:
+        [0.8, 1.0, 0.5, 0.33, 0.25],
+        envelope(0.005, 0.3, 0.7, 0.1),
+        ratios: Cow::Borrowed(&[0.5, 1.0, 2.0, 3.0, 4.0])
+    }
+    // Pad 1 (new age)
+    make_instrument!{
+        NEW_AGE_PAD:
+        [1.0, 0.4, 0.3, 0.2],
+        envelope(0.1, 1.0, 0.6, 0.8),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 3.0, 5.04]),
+        damping: 1.0
+    }
+    // Pad 2 (warm)
+    make_instrument!{
+        WARM_PAD:
+        [1.0, 0.8, 0.15, 0.05],
+        envelope(0.4, 0.5, 0.9, 0.8),
+        ratios: Cow::Borrowed(&[1.0, 1.003, 2.0, 3.0])
+    }
+    // Pad 3 (polysynth)
+    make_instrument!{
+        POLYSYNTH_PAD:
+        [0.8, 0.8, 0.4, 0.25, 0.2],
+        envelope(0.05, 0.5, 0.7, 0.4),
+        ratios: Cow::Borrowed(&[1.0, 1.006, 2.0, 3.0, 4.0])
+    }
+    // Pad 4 (choir)
+    make_instrument!{
+        CHOIR_PAD:
+        [1.0, 0.5, 0.3, 0.6, 0.8, 0.3],
+        envelope(0.5, 0.5, 0.9, 1.0),
+        noise: 0.1,
+        vibrato: (4.5, 0.08)
+    }
+    // Pad 5 (bowed)
+    make_instrument!{
+        BOWED_PAD:
+        [1.0, 0.6, 0.4, 0.2],
+        envelope(0.6, 0.5, 0.8, 0.8),
+        noise: 0.2
+    }
+    // Pad 6 (metallic)
+    make_instrument!{
+        METALLIC_PAD:
+        [1.0, 0.5, 0.4, 0.3],
+        envelope(0.3, 1.0, 0.6, 1.0),
+        ratios: Cow::Borrowed(&[1.0, 2.4, 3.9, 5.6])
+    }
+    // Pad 7 (halo)
+    make_instrument!{
+        HALO_PAD:
+        [1.0, 0.3, 0.4, 0.1, 0.2],
+        envelope(0.6, 0.5, 0.8, 1.2),
+        vibrato: (3.0, 0.05)
+    }
+    // Pad 8 (sweep)
+    make_instrument!{
+        SWEEP_PAD:
+        [1.0, 0.7, 0.6, 0.5, 0.4, 0.3, 0.2],
+        envelope(0.5, 1.5, 0.5, 1.0),
+        damping: 1.5
+    }
+    // FX 1 (rain)
+    make_instrument!{
+        RAIN:
+        [1.0, 0.4, 0.2],
+        envelope(0.001, 0.6, 0.0, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.7, 4.1]),
+        damping: 0.1,
+        noise: 0.6
+    }
+    // FX 2 (soundtrack)
+    make_instrument!{
+        SOUNDTRACK:
+        [1.0, 0.5, 0.3],
+        envelope(0.8, 1.0, 0.7, 1.5),
+        ratios: Cow::Borrowed(&[1.0, 1.5, 2.0])
+    }
+    // FX 3 (crystal)
+    make_instrument!{
+        CRYSTAL:
+        [1.0, 0.6, 0.4, 0.2],
+        envelope(0.001, 2.0, 0.0, 0.8),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 4.2, 6.3]),
+        damping: 0.3
+    }
+    // FX 4 (atmosphere)
+    make_instrument!{
+        ATMOSPHERE:
+        [1.0, 0.6, 0.3, 0.2],
+        envelope(0.01, 1.5, 0.4, 1.0),
+        noise: 0.3
+    }
+    // FX 5 (brightness)
+    make_instrument!{
+        BRIGHTNESS:
+        [1.0, 0.8, 0.8, 0.7, 0.6, 0.6, 0.5],
+        envelope(0.2, 1.0, 0.7, 1.0)
+    }
+    // FX 6 (goblins)
+    make_instrument!{
+        GOBLINS:
+        [1.0, 0.6, 0.4],
+        envelope(0.8, 1.0, 0.7, 1.0),
+        ratios: Cow::Borrowed(&[1.0, 1.41, 2.1]),
+        vibrato: (2.0, 0.5)
+    }
+    // FX 7 (echoes)
+    make_instrument!{
+        ECHOES:
+        [1.0, 0.4, 0.2],
+        envelope(0.05, 0.5, 0.6, 1.5),
+        tremolo: (3.0, 0.5)
+    }
+    // FX 8 (sci-fi)
+    make_instrument!{
+        SCI_FI:
+        [1.0, 0.5, 0.4],
+        envelope(0.3, 0.5, 0.7, 0.8),
+        ratios: Cow::Borrowed(&[1.0, 2.5, 3.3]),
+        vibrato: (7.0, 0.8)
+    }
+    // Sitar
+    make_instrument!{
+        SITAR:
+        [1.0, 0.9, 0.8, 0.8, 0.7, 0.6, 0.6, 0.5, 0.4, 0.4],
+        envelope(0.001, 2.0, 0.1, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.002, 3.005, 4.01, 5.015, 6.02, 7.03, 8.04, 9.05, 10.06]),
+        damping: 1.0,
+        noise: 0.2
+    }
+    // Banjo
+    make_instrument!{
+        BANJO:
+        [1.0, 0.9, 0.8, 0.7, 0.5, 0.4, 0.3],
+        envelope(0.001, 0.6, 0.0, 0.1),
+        damping: 0.1
+    }
+    // Shamisen
+    make_instrument!{
+        SHAMISEN:
+        [1.0, 0.8, 0.7, 0.5, 0.4, 0.3],
+        envelope(0.001, 0.8, 0.0, 0.1),
+        damping: 0.15,
+        noise: 0.3
+    }
+    // Koto
+    make_instrument!{
+        KOTO:
+        [1.0, 0.6, 0.4, 0.3, 0.2],
+        envelope(0.001, 1.5, 0.0, 0.3),
+        damping: 0.3
+    }
+    // Kalimba
+    make_instrument!{
+        KALIMBA:
+        [1.0, 0.2, 0.05],
+        envelope(0.001, 0.8, 0.0, 0.2),
+        ratios: Cow::Borrowed(&[1.0, 5.9, 12.0]),
+        damping: 0.05
+    }
+    // Bag pipe
+    make_instrument!{
+        BAGPIPE:
+        [0.5, 0.8, 1.0, 0.9, 0.8, 0.6, 0.5],
+        envelope(0.05, 0.1, 0.9, 0.1),
+        ratios: Cow::Borrowed(&[0.5, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+        noise: 0.2
+    }
+    // Fiddle
+    make_instrument!{
+        FIDDLE:
+        [1.0, 0.8, 0.7, 0.7, 0.5, 0.4, 0.4, 0.3],
+        envelope(0.02, 0.2, 0.8, 0.1),
+        noise: 0.2,
+        vibrato: (6.0, 0.12)
+    }
+    // Shanai
+    make_instrument!{
+        SHANAI:
+        [0.6, 1.0, 0.9, 0.7, 0.5, 0.4],
+        envelope(0.03, 0.1, 0.9, 0.1),
+        noise: 0.2,
+        vibrato: (6.0, 0.15)
+    }
+    // Tinkle Bell
+    make_instrument!{
+        TINKLE_BELL:
+        [1.0, 0.4, 0.2],
+        envelope(0.001, 0.8, 0.0, 0.3),
+        ratios: Cow::Borrowed(&[1.0, 2.76, 5.4]),
+        damping: 0.1
+    }
+    // Agogo
+    make_instrument!{
+        AGOGO:
+        [1.0, 0.5, 0.2],
+        envelope(0.001, 0.4, 0.0, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 2.5, 4.1]),
+        damping: 0.05
+    }
+    // Steel Drums
+    make_instrument!{
+        STEEL_DRUMS:
+        [1.0, 0.6, 0.3, 0.25, 0.1],
+        envelope(0.001, 0.8, 0.0, 0.2),
+        ratios: Cow::Borrowed(&[1.0, 2.0, 3.0, 3.92, 5.1]),
+        damping: 0.2
+    }
+    // Woodblock
+    make_instrument!{
+        WOODBLOCK:
+        [1.0, 0.3],
+        envelope(0.001, 0.08, 0.0, 0.02),
+        ratios: Cow::Borrowed(&[1.0, 2.6]),
+        noise: 0.5
+    }
+    // Taiko Drum
+    make_instrument!{
+        TAIKO:
+        [1.0, 0.5, 0.3],
+        envelope(0.001, 0.6, 0.0, 0.2),
+        ratios: Cow::Borrowed(&[1.0, 1.6, 2.3]),
+        damping: 0.1,
+        noise: 1.0
+    }
+    // Melodic Tom
+    make_instrument!{
+        MELODIC_TOM:
+        [1.0, 0.4, 0.2],
+        envelope(0.001, 0.4, 0.0, 0.1),
+        ratios: Cow::Borrowed(&[1.0, 1.5, 2.0]),
+        damping: 0.1,
+        noise: 0.6
+    }
+    // Synth Drum
+    make_instrument!{
+        SYNTH_DRUM:
+        [1.0, 0.2],
+        envelope(0.001, 0.3, 0.0, 0.05),
+        noise: 0.3,
+        vibrato: (3.0, 1.0)
+    }
+    // Reverse Cymbal
+    make_instrument!{
+        REVERSE_CYMBAL:
+        [0.0],
+        envelope(1.2, 0.05, 0.0, 0.05),
+        noise: 8.0
+    }
+    // Guitar Fret Noise
+    make_instrument!{
+        FRET_NOISE:
+        [0.2, 0.1],
+        envelope(0.001, 0.1, 0.0, 0.05),
+        noise: 6.0
+    }
+    // Breath Noise
+    make_instrument!{
+        BREATH_NOISE:
+        [0.0],
+        envelope(0.05, 0.2, 0.7, 0.15),
+        noise: 6.0
+    }
+    // Seashore
+    make_instrument!{
+        SEASHORE:
+        [0.0],
+        envelope(1.0, 2.0, 0.5, 1.5),
+        noise: 10.0,
+        tremolo: (0.2, 0.8)
+    }
+    // Bird Tweet
+    make_instrument!{
+        BIRD_TWEET:
+        [1.0],
+        envelope(0.01, 0.2, 0.5, 0.1),
+        ratios: Cow::Borrowed(&[4.0]),
+        vibrato: (12.0, 2.0)
+    }
+    // Telephone Ring
+    make_instrument!{
+        TELEPHONE_RING:
+        [1.0, 0.8],
+        envelope(0.001, 0.01, 1.0, 0.05),
+        ratios: Cow::Borrowed(&[2.0, 2.5]),
+        tremolo: (20.0, 1.0)
+    }
+    // Helicopter
+    make_instrument!{
+        HELICOPTER:
+        [0.5, 0.3],
+        envelope(0.3, 0.1, 1.0, 0.5),
+        noise: 6.0,
+        tremolo: (12.0, 0.9)
+    }
+    // Applause
+    make_instrument!{
+        APPLAUSE:
+        [0.0],
+        envelope(0.4, 0.5, 0.8, 1.0),
+        noise: 8.0,
+        tremolo: (9.0, 0.6)
+    }
+    // Gunshot
+    make_instrument!{
+        GUNSHOT:
+        [0.3],
+        envelope(0.001, 0.3, 0.0, 0.2),
+        damping: 0.05,
+        noise: 8.0
     }
 }
 
-pub(super) static INSTRUMENTS: &'static [Instrument] = &[
+pub(super) static INSTRUMENTS: &[Instrument] = &[
     // Piano
     Instrument::PIANO, // 0
-    Instrument::TODO, // 1
-    Instrument::TODO, // 2
-    Instrument::TODO, // 3
-    Instrument::TODO, // 4
-    Instrument::TODO, // 5
-    Instrument::TODO, // 6
-    Instrument::TODO, // 7
+    Instrument::BRIGHT_PIANO, // 1
+    Instrument::ELECTRIC_GRAND, // 2
+    Instrument::HONKY_TONK, // 3
+    Instrument::ELECTRIC_PIANO, // 4
+    Instrument::FM_PIANO, // 5
+    Instrument::HARPSICHORD, // 6
+    Instrument::CLAVINET, // 7
 
     // Chromatic Percussion
-    Instrument::TODO, // 8
-    Instrument::TODO, // 9
-    Instrument::TODO, // 10
-    Instrument::TODO, // 11
-    Instrument::TODO, // 12
-    Instrument::TODO, // 13
-    Instrument::TODO, // 14
-    Instrument::TODO, // 15
+    Instrument::CELESTA, // 8
+    Instrument::GLOCKENSPIEL, // 9
+    Instrument::MUSIC_BOX, // 10
+    Instrument::VIBRAPHONE, // 11
+    Instrument::MARIMBA, // 12
+    Instrument::XYLOPHONE, // 13
+    Instrument::TUBULAR_BELLS, // 14
+    Instrument::DULCIMER, // 15
 
     // Organ
-    Instrument::TODO, // 16
-    Instrument::TODO, // 17
-    Instrument::TODO, // 18
-    Instrument::TODO, // 19
-    Instrument::TODO, // 20
-    Instrument::TODO, // 21
-    Instrument::TODO, // 22
-    Instrument::TODO, // 23
+    Instrument::DRAWBAR_ORGAN, // 16
+    Instrument::PERCUSSIVE_ORGAN, // 17
+    Instrument::ROCK_ORGAN, // 18
+    Instrument::CHURCH_ORGAN, // 19
+    Instrument::REED_ORGAN, // 20
+    Instrument::ACCORDION, // 21
+    Instrument::HARMONICA, // 22
+    Instrument::TANGO_ACCORDION, // 23
 
     // Guitar
     Instrument::GUITAR, // 24
-    Instrument::TODO, // 25
-    Instrument::TODO, // 26
-    Instrument::TODO, // 27
-    Instrument::TODO, // 28
-    Instrument::TODO, // 29
-    Instrument::TODO, // 30
-    Instrument::TODO, // 31
+    Instrument::STEEL_GUITAR, // 25
+    Instrument::JAZZ_GUITAR, // 26
+    Instrument::CLEAN_GUITAR, // 27
+    Instrument::MUTED_GUITAR, // 28
+    Instrument::OVERDRIVEN_GUITAR, // 29
+    Instrument::DISTORTION_GUITAR, // 30
+    Instrument::GUITAR_HARMONICS, // 31
 
     // Bass
-    Instrument::TODO, // 32
-    Instrument::TODO, // 33
-    Instrument::TODO, // 34
-    Instrument::TODO, // 35
-    Instrument::TODO, // 36
-    Instrument::TODO, // 37
-    Instrument::TODO, // 38
-    Instrument::TODO, // 39
+    Instrument::ACOUSTIC_BASS, // 32
+    Instrument::FINGER_BASS, // 33
+    Instrument::PICK_BASS, // 34
+    Instrument::FRETLESS_BASS, // 35
+    Instrument::SLAP_BASS, // 36
+    Instrument::SLAP_BASS_2, // 37
+    Instrument::SYNTH_BASS, // 38
+    Instrument::SYNTH_BASS_2, // 39
 
     // Strings
     Instrument::VIOLIN, // 40
-    Instrument::VIOLIN, // 41
-    Instrument::VIOLIN, // 42
-    Instrument::VIOLIN, // 43
-    Instrument::VIOLIN, // 44
+    Instrument::VIOLA, // 41
+    Instrument::CELLO, // 42
+    Instrument::CONTRABASS, // 43
+    Instrument::TREMOLO_STRINGS, // 44
     Instrument::PIZZICATO_STRINGS, // 45
-    Instrument::TODO, // 46
-    Instrument::TODO, // 47
+    Instrument::HARP, // 46
+    Instrument::TIMPANI, // 47
 
     // Ensemble
-    Instrument::TODO, // 48
-    Instrument::TODO, // 49
-    Instrument::TODO, // 50
-    Instrument::TODO, // 51
-    Instrument::TODO, // 52
-    Instrument::TODO, // 53
-    Instrument::TODO, // 54
-    Instrument::TODO, // 55
+    Instrument::STRING_ENSEMBLE, // 48
+    Instrument::SLOW_STRINGS, // 49
+    Instrument::SYNTH_STRINGS, // 50
+    Instrument::SYNTH_STRINGS_2, // 51
+    Instrument::CHOIR, // 52
+    Instrument::VOICE_OOHS, // 53
+    Instrument::SYNTH_VOICE, // 54
+    Instrument::ORCHESTRA_HIT, // 55
 
     // Brass
-    Instrument::TODO, // 56
-    Instrument::TODO, // 57
-    Instrument::TODO, // 58
-    Instrument::TODO, // 59
-    Instrument::TODO, // 60
-    Instrument::TODO, // 61
-    Instrument::TODO, // 62
-    Instrument::TODO, // 63
+    Instrument::TRUMPET, // 56
+    Instrument::TROMBONE, // 57
+    Instrument::TUBA, // 58
+    Instrument::MUTED_TRUMPET, // 59
+    Instrument::FRENCH_HORN, // 60
+    Instrument::BRASS_SECTION, // 61
+    Instrument::SYNTH_BRASS, // 62
+    Instrument::SYNTH_BRASS_2, // 63
 
     // Reed
-    Instrument::TODO, // 64
-    Instrument::TODO, // 65
-    Instrument::TODO, // 66
-    Instrument::TODO, // 67
-    Instrument::TODO, // 68
-    Instrument::TODO, // 69
-    Instrument::TODO, // 70
-    Instrument::TODO, // 71
+    Instrument::SOPRANO_SAX, // 64
+    Instrument::ALTO_SAX, // 65
+    Instrument::TENOR_SAX, // 66
+    Instrument::BARITONE_SAX, // 67
+    Instrument::OBOE, // 68
+    Instrument::ENGLISH_HORN, // 69
+    Instrument::BASSOON, // 70
+    Instrument::CLARINET, // 71
 
     // Pipe
-    Instrument::TODO, // 72
+    Instrument::PICCOLO, // 72
     Instrument::FLUTE, // 73
     Instrument::RECORDER, // 74
-    Instrument::TODO, // 75
-    Instrument::TODO, // 76
-    Instrument::TODO, // 77
-    Instrument::TODO, // 78
-    Instrument::TODO, // 79
+    Instrument::PAN_FLUTE, // 75
+    Instrument::BLOWN_BOTTLE, // 76
+    Instrument::SHAKUHACHI, // 77
+    Instrument::WHISTLE, // 78
+    Instrument::OCARINA, // 79
 
     // Synth Lead
     Instrument::SQUARE_SYNTH, // 80
     Instrument::SAW_SYNTH, // 81
-    Instrument::TODO, // 82
-    Instrument::TODO, // 83
-    Instrument::TODO, // 84
-    Instrument::TODO, // 85
-    Instrument::TODO, // 86
-    Instrument::TODO, // 87
+    Instrument::CALLIOPE, // 82
+    Instrument::CHIFF, // 83
+    Instrument::CHARANG, // 84
+    Instrument::VOICE_LEAD, // 85
+    Instrument::FIFTHS_LEAD, // 86
+    Instrument::BASS_LEAD, // 87
 
     // Synth Pad
-    Instrument::TODO, // 88
-    Instrument::TODO, // 89
-    Instrument::TODO, // 90
-    Instrument::TODO, // 91
-    Instrument::TODO, // 92
-    Instrument::TODO, // 93
-    Instrument::TODO, // 94
-    Instrument::TODO, // 95
+    Instrument::NEW_AGE_PAD, // 88
+    Instrument::WARM_PAD, // 89
+    Instrument::POLYSYNTH_PAD, // 90
+    Instrument::CHOIR_PAD, // 91
+    Instrument::BOWED_PAD, // 92
+    Instrument::METALLIC_PAD, // 93
+    Instrument::HALO_PAD, // 94
+    Instrument::SWEEP_PAD, // 95
 
     // Synth Effects
-    Instrument::TODO, // 96
-    Instrument::TODO, // 97
-    Instrument::TODO, // 98
-    Instrument::TODO, // 99
-    Instrument::TODO, // 100
-    Instrument::TODO, // 101
-    Instrument::TODO, // 102
-    Instrument::TODO, // 103
+    Instrument::RAIN, // 96
+    Instrument::SOUNDTRACK, // 97
+    Instrument::CRYSTAL, // 98
+    Instrument::ATMOSPHERE, // 99
+    Instrument::BRIGHTNESS, // 100
+    Instrument::GOBLINS, // 101
+    Instrument::ECHOES, // 102
+    Instrument::SCI_FI, // 103
 
     // Ethnic
-    Instrument::TODO, // 104
-    Instrument::TODO, // 105
-    Instrument::TODO, // 106
-    Instrument::TODO, // 107
-    Instrument::TODO, // 108
-    Instrument::TODO, // 109
-    Instrument::TODO, // 110
-    Instrument::TODO, // 111
+    Instrument::SITAR, // 104
+    Instrument::BANJO, // 105
+    Instrument::SHAMISEN, // 106
+    Instrument::KOTO, // 107
+    Instrument::KALIMBA, // 108
+    Instrument::BAGPIPE, // 109
+    Instrument::FIDDLE, // 110
+    Instrument::SHANAI, // 111
 
     // Percussive
-    Instrument::TODO, // 112
-    Instrument::TODO, // 113
-    Instrument::TODO, // 114
-    Instrument::TODO, // 115
-    Instrument::TODO, // 116
-    Instrument::TODO, // 117
-    Instrument::TODO, // 118
-    Instrument::TODO, // 119
+    Instrument::TINKLE_BELL, // 112
+    Instrument::AGOGO, // 113
+    Instrument::STEEL_DRUMS, // 114
+    Instrument::WOODBLOCK, // 115
+    Instrument::TAIKO, // 116
+    Instrument::MELODIC_TOM, // 117
+    Instrument::SYNTH_DRUM, // 118
+    Instrument::REVERSE_CYMBAL, // 119
 
     // Sound Effects
-    Instrument::TODO, // 120
-    Instrument::TODO, // 121
-    Instrument::TODO, // 122
-    Instrument::TODO, // 123
-    Instrument::TODO, // 124
-    Instrument::TODO, // 125
-    Instrument::TODO, // 126
-    Instrument::TODO, // 127
-];
\ No newline at end of file
+    Instrument::FRET_NOISE, // 120
+    Instrument::BREATH_NOISE, // 121
+    Instrument::SEASHORE, // 122
+    Instrument::BIRD_TWEET, // 123
+    Instrument::TELEPHONE_RING, // 124
+    Instrument::HELICOPTER, // 125
+    Instrument::APPLAUSE, // 126
+    Instrument::GUNSHOT, // 127
+];
@@ -0,0 +1,534 @@
+//! The synthesizer behind the player, for use in other programs.
+//!
+//! A [`Synth`] takes MIDI messages and renders stereo audio from them:
+//!
+//! ```
+//! let mut synth = player::Synth::new(44100, player::DEFAULT_POLYPHONY, player::StealPolicy::default());
+//! synth.handle_midi(&[0x90, 60, 100]);
+//! let mut buf = [0.0; 256];
+//! synth.render(&mut buf);
+//! ```
+
+use std::{borrow::Cow, sync::Arc};
+
+mod sys {
+    extern "C" {
+        #[allow(dead_code)]
+        pub(super) fn opt_sin(x: f64) -> f64;
+    }
+}
+
+#[allow(dead_code)]
+fn opt_sin(x: f64) -> f64 {
+    unsafe { sys::opt_sin(x) }
+}
+
+/// Default for how many melodic notes may sound at once
+pub const DEFAULT_POLYPHONY: usize = 64;
+/// How many drums may sound at once; as many again may be fading out
+const DRUM_NOTE_COUNT: usize = 16;
+const CHANNEL_COUNT: usize = 16;
+/// General MIDI channel 10
+const PERCUSSION_CHANNEL: usize = 9;
+/// Default sample rate
+pub const DEFAULT_RATE: u32 = 44100;
+
+#[derive(Debug, Default, Clone)]
+struct Note {
+    note: u8,
+    amp: u32,
+    /// Unbent frequency. The channel's pitch bend applies on top of this.
+    freq: f64,
+    /// Zero indicates this not is not in use
+    sample_time: u64,
+    /// Zero indicates that this note is ongoing
+    /// Positive value indicates that this note should stop at that sample time
+    stop_time: u64,
+    /// Current sin() argument. Keeping track of this helps pitch-bending to not sound bad.
+    current_parameter: f64,
+    instrument: Arc<Instrument>,
+    /// The instrument's envelope, at the sample rate the note is played at
+    envelope: SampleEnvelope,
+    /// The key has been let go, but a pedal is keeping the note from releasing
+    key_up: bool,
+    /// The key was down when the sostenuto pedal was pressed
+    sostenuto: bool,
+    channel: usize,
+    /// Order in which notes started, for voice stealing
+    age: u64,
+    /// Zero indicates this note has not been stolen
+    /// Positive value is the sample time at which it was stolen, and started fading out
+    stolen_at: u64,
+    /// Most recent amplitude, for voice stealing
+    level: f64,
+    /// For instruments with noise
+    noise: percussion::Noise,
+    /// The preset and index of the zone this note plays, if it comes from a soundfont rather than
+    /// an instrument
+    zone: Option<(Arc<soundfont::Preset>, usize)>,
+    /// Where in the zone's sample playback is
+    position: f64,
+}
+
+impl Note {
+    fn release(&mut self) {
+        self.stop_time = self.sample_time + self.envelope.release;
+    }
+}
+
+/// Registered parameter number for pitch bend sensitivity
+const RPN_PITCH_BEND_RANGE: (u8, u8) = (0, 0);
+/// Registered parameter number meaning no parameter is selected
+const RPN_NULL: (u8, u8) = (127, 127);
+/// How much the soft pedal attenuates notes played while it is held
+const SOFT_PEDAL_GAIN: f64 = 0.6;
+
+/// Per-channel state set by controllers and other channel messages
+#[derive(Debug, Clone)]
+struct Channel {
+    program: u8,
+    instrument: Arc<Instrument>,
+    /// From -1.0 (lowest) to just under 1.0 (highest)
+    pitch_bend: f64,
+    /// How many semitones a full pitch bend shifts by
+    bend_range: f64,
+    /// Registered parameter (MSB, LSB) that data entry controllers apply to
+    rpn: (u8, u8),
+    /// Channel volume (CC7)
+    volume: u8,
+    /// Expression (CC11), a fraction of the channel volume
+    expression: u8,
+    /// Pan (CC10), 0 is hard left, 64 is center and 127 is hard right
+    pan: u8,
+    /// Sustain pedal (CC64)
+    sustain: bool,
+    /// Sostenuto pedal (CC66)
+    sostenuto: bool,
+    /// Soft pedal (CC67)
+    soft: bool,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Channel {
+            program: 0,
+            instrument: Default::default(),
+            pitch_bend: 0.0,
+            bend_range: 2.0,
+            rpn: RPN_NULL,
+            volume: 100,
+            expression: 127,
+            pan: 64,
+            sustain: false,
+            sostenuto: false,
+            soft: false,
+        }
+    }
+}
+
+impl Channel {
+    /// What to multiply the frequency of this channel's notes by
+    fn bend_factor(&self) -> f64 {
+        (2.0f64).powf(self.pitch_bend * self.bend_range / 12.0)
+    }
+
+    /// Left and right gain for this channel, from its volume, expression and pan
+    fn gains(&self) -> (f64, f64) {
+        // Volume and expression follow the General MIDI curve of 40 * log10(value / 127) dB
+        let volume = (self.volume as f64 / 127.0).powi(2) * (self.expression as f64 / 127.0).powi(2);
+        // Equal-power pan law, with 0 and 1 both hard left so that 64 is exactly center
+        let angle = self.pan.saturating_sub(1) as f64 / 126.0 * std::f64::consts::FRAC_PI_2;
+        (volume * angle.cos(), volume * angle.sin())
+    }
+
+    /// Whether a pedal keeps this note sounding after its key is let go
+    fn holds(&self, note: &Note) -> bool {
+        self.sustain || (self.sostenuto && note.sostenuto)
+    }
+
+    /// Catches or lets go of this channel's notes after a pedal moved.
+    /// `sostenuto_pressed` is whether the sostenuto pedal just went down.
+    fn update_pedals<'a>(&self, notes: impl IntoIterator<Item = &'a mut Note>, sostenuto_pressed: bool) {
+        for note in notes {
+            if note.stop_time != 0 {
+                continue;
+            }
+            if sostenuto_pressed && !note.key_up {
+                note.sostenuto = true;
+            } else if !self.sostenuto {
+                note.sostenuto = false;
+            }
+            if note.key_up && !self.holds(note) {
+                note.release();
+            }
+        }
+    }
+
+    fn controller(&mut self, controller: u8, value: u8) {
+        match controller {
+            7 => self.volume = value,
+            10 => self.pan = value,
+            11 => self.expression = value,
+            64 => self.sustain = value >= 64,
+            66 => self.sostenuto = value >= 64,
+            67 => self.soft = value >= 64,
+            // Data entry MSB
+            6 if self.rpn == RPN_PITCH_BEND_RANGE => {
+                self.bend_range = value as f64 + self.bend_range.fract();
+            }
+            // Data entry LSB, in cents
+            38 if self.rpn == RPN_PITCH_BEND_RANGE => {
+                self.bend_range = self.bend_range.trunc() + value.min(99) as f64 / 100.0;
+            }
+            // Non-registered parameter select: data entry no longer applies to an RPN
+            98 | 99 => self.rpn = RPN_NULL,
+            100 => self.rpn.1 = value,
+            101 => self.rpn.0 = value,
+            // Reset All Controllers
+            121 => {
+                self.pitch_bend = 0.0;
+                self.expression = 127;
+                self.sustain = false;
+                self.sostenuto = false;
+                self.soft = false;
+                self.rpn = RPN_NULL;
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Times are in seconds
+#[derive(Debug, Clone, Copy, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Envelope {
+    attack: f64,
+    decay: f64,
+    sustain: f64,
+    release: f64,
+}
+
+impl Default for Envelope {
+    fn default() -> Self {
+        Envelope::DEFAULT_ENVELOPE
+    }
+}
+
+impl Envelope {
+    fn to_samples(self, rate: u32) -> SampleEnvelope {
+        let samples = |seconds: f64| (seconds * rate as f64).round() as u64;
+        SampleEnvelope {
+            attack: samples(self.attack),
+            decay: samples(self.decay),
+            sustain: self.sustain,
+            release: samples(self.release),
+        }
+    }
+}
+
+/// An `Envelope` with times in samples
+#[derive(Debug, Default, Clone, Copy)]
+struct SampleEnvelope {
+    attack: u64,
+    decay: u64,
+    sustain: f64,
+    release: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct NoteShouldStop;
+
+impl SampleEnvelope {
+    fn envelope(&self, sample_time: u64, stop_time: u64) -> Result<f64, NoteShouldStop> {
+        Ok(if sample_time < stop_time { // Release
+            let inverse_progress = (stop_time - sample_time) as f64 / self.release as f64;
+            // (1.0 - progress) * self.sustain
+            inverse_progress * self.sustain
+        } else if sample_time < self.attack {
+            sample_time as f64 / self.attack as f64
+        } else if (sample_time - self.attack) < self.decay {
+            let progress = (sample_time - self.attack) as f64 / self.decay as f64;
+            (1.0 - progress) + progress * self.sustain
+        } else if self.sustain <= 0.0001 { // End after decay if sustain ~= 0
+            return Err(NoteShouldStop);
+        } else { // Normal sustain volume
+            self.sustain
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Instrument {
+    amplitudes: Cow<'static, [f64]>,
+    /// Frequency of each partial, relative to the note. Partials past the end of this are
+    /// harmonics, so leaving it empty gives the harmonic series.
+    ratios: Cow<'static, [f64]>,
+    #[allow(dead_code)]
+    full_amplitude: f64,
+    envelope: Envelope,
+    /// Time constant in seconds with which each partial dies away relative to the one below it,
+    /// so that the sound gets duller as it decays. Zero keeps every partial under the envelope.
+    damping: f64,
+    /// Amount of noise around the note's pitch, for breath, bowing and effects
+    noise: f64,
+    /// Rate in Hz and depth in semitones of pitch modulation
+    vibrato: (f64, f64),
+    /// Rate in Hz and depth, from 0.0 to 1.0, of loudness modulation
+    tremolo: (f64, f64),
+}
+mod bank;
+mod instrument;
+mod midi;
+mod percussion;
+mod soundfont;
+mod synth;
+mod voices;
+pub use bank::{Bank, BankError};
+pub use soundfont::{SoundFont, SoundFontError};
+pub use synth::Synth;
+pub use voices::StealPolicy;
+pub use midly::live::LiveEvent;
+
+impl Default for Instrument {
+    fn default() -> Self {
+        instrument::INSTRUMENTS[0].clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn envelope() {
+        let piano = &instrument::INSTRUMENTS[0];
+        let envelope = piano.envelope.to_samples(DEFAULT_RATE);
+        assert_eq!(envelope.envelope(0, 0), Ok(0.0));
+        assert_eq!(envelope.envelope(envelope.attack / 2, 0), Ok(0.5));
+        assert_eq!(envelope.envelope(envelope.attack, 0), Ok(1.0));
+        assert_eq!(envelope.envelope(envelope.attack + envelope.decay / 2, 0), Ok(1.0 - (1.0 - envelope.sustain) * 0.5));
+        assert_eq!(envelope.envelope(envelope.attack + envelope.decay, 0), Ok(envelope.sustain));
+    }
+
+    #[test]
+    fn pitch_bend_range() {
+        let mut channel = Channel { pitch_bend: 0.5, ..Channel::default() };
+        assert_eq!(channel.bend_factor(), (2.0f64).powf(1.0 / 12.0));
+        for (controller, value) in [(101, 0), (100, 0), (6, 12), (38, 50)] {
+            channel.controller(controller, value);
+        }
+        assert_eq!(channel.bend_range, 12.5);
+        channel.controller(121, 0);
+        assert_eq!(channel.pitch_bend, 0.0);
+    }
+
+    #[test]
+    fn pan_and_volume() {
+        let mut channel = Channel { volume: 127, ..Channel::default() };
+        let (left, right) = channel.gains();
+        assert!((left - right).abs() < 1e-12);
+        assert!((left * left + right * right - 1.0).abs() < 1e-12);
+        channel.controller(10, 0);
+        assert_eq!(channel.gains(), (1.0, 0.0));
+        channel.controller(11, 0);
+        assert_eq!(channel.gains(), (0.0, 0.0));
+        channel.controller(121, 0);
+        assert_eq!(channel.gains(), (1.0, 0.0));
+    }
+
+    #[test]
+    fn pedals() {
+        let mut channel = Channel::default();
+        let playing = Note { sample_time: 1, ..Note::default() };
+        let mut notes = [playing.clone(), playing.clone()];
+
+        // Sustain holds a note whose key is let go, until the pedal is let go
+        channel.controller(64, 127);
+        notes[0].key_up = true;
+        channel.update_pedals(&mut notes, false);
+        assert_eq!(notes[0].stop_time, 0);
+        channel.controller(64, 0);
+        channel.update_pedals(&mut notes, false);
+        assert_ne!(notes[0].stop_time, 0);
+
+        // Sostenuto only holds notes whose keys were down when it was pressed
+        notes = [playing.clone(), playing];
+        channel.controller(66, 127);
+        channel.update_pedals(&mut notes[..1], true);
+        notes[0].key_up = true;
+        notes[1].key_up = true;
+        channel.update_pedals(&mut notes, false);
+        assert_eq!(notes[0].stop_time, 0);
+        assert_ne!(notes[1].stop_time, 0);
+        channel.controller(66, 0);
+        channel.update_pedals(&mut notes, false);
+        assert_ne!(notes[0].stop_time, 0);
+    }
+
+    #[test]
+    fn voice_stealing() {
+        let start = |voices: &mut voices::Voices, note| voices.start(Note { note, sample_time: 1, ..Note::default() });
+        let stolen = |voices: &voices::Voices| -> Vec<u8> {
+            voices.iter().filter(|note| note.stolen_at != 0).map(|note| note.note).collect()
+        };
+
+        let mut voices = voices::Voices::new(2, voices::StealPolicy::Oldest);
+        start(&mut voices, 60);
+        start(&mut voices, 62);
+        start(&mut voices, 64);
+        assert_eq!(stolen(&voices), [60]);
+        assert_eq!(voices.channel(0).count(), 2);
+
+        let mut voices = voices::Voices::new(2, voices::StealPolicy::Releasing);
+        start(&mut voices, 60);
+        start(&mut voices, 62);
+        voices.channel(0).find(|note| note.note == 62).unwrap().release();
+        start(&mut voices, 64);
+        assert_eq!(stolen(&voices), [62]);
+
+        let mut voices = voices::Voices::new(2, voices::StealPolicy::Quietest);
+        start(&mut voices, 60);
+        start(&mut voices, 62);
+        voices.channel(0).for_each(|note| note.level = note.note as f64);
+        start(&mut voices, 64);
+        assert_eq!(stolen(&voices), [60]);
+    }
+
+
+    #[test]
+    fn midi_bytes_and_events() {
+        let render = |synth: &mut Synth| {
+            let mut buf = [0.0; 512];
+            synth.render(&mut buf);
+            buf
+        };
+        let mut bytes = Synth::new(DEFAULT_RATE, DEFAULT_POLYPHONY, StealPolicy::default());
+        let mut events = Synth::new(DEFAULT_RATE, DEFAULT_POLYPHONY, StealPolicy::default());
+
+        // A chord with running status, split mid-message
+        bytes.handle_midi(&[0x90, 60, 100, 64]);
+        bytes.handle_midi(&[100]);
+        for key in [60, 64] {
+            events.handle_event(LiveEvent::Midi {
+                channel: 0.into(),
+                message: midly::MidiMessage::NoteOn { key: key.into(), vel: 100.into() },
+            });
+        }
+        let sound = render(&mut bytes);
+        assert!(sound.iter().any(|&sample| sample != 0.0));
+        assert_eq!(sound, render(&mut events));
+    }
+
+    #[test]
+    #[should_panic(expected = "whole stereo frames")]
+    fn render_odd_length() {
+        let mut synth = Synth::new(DEFAULT_RATE, DEFAULT_POLYPHONY, StealPolicy::default());
+        synth.render(&mut [0.0; 3]);
+    }
+
+    #[test]
+    fn soundfont_presets() {
+        let soundfont = SoundFont::parse(&soundfont::tests::fixture()).unwrap();
+        let mut synth = Synth::new(DEFAULT_RATE, DEFAULT_POLYPHONY, StealPolicy::default());
+        synth.set_bank(Bank::builtin().with_soundfont(soundfont));
+        let render = |synth: &mut Synth, seconds: f64| {
+            let mut buf = vec![0.0; (DEFAULT_RATE as f64 * seconds) as usize * 2];
+            synth.render(&mut buf);
+            buf
+        };
+
+        // Both layers loop for as long as the key is held, well past the end of the sample
+        synth.handle_midi(&[0x90, 61, 110]);
+        assert!(render(&mut synth, 0.5)[DEFAULT_RATE as usize / 2..].iter().any(|&sample| sample != 0.0));
+        // A note-off releases both
+        synth.handle_midi(&[0x80, 61, 0]);
+        render(&mut synth, 1.1);
+        assert!(synth.is_silent());
+
+        // The drum kit's click plays to the end of its sample
+        synth.handle_midi(&[0x99, 69, 100]);
+        assert!(render(&mut synth, 0.001).iter().any(|&sample| sample != 0.0));
+        render(&mut synth, 0.001);
+        assert!(synth.is_silent());
+        // Channel 10's program chooses the kit, falling back to kit 0 for programs without one
+        synth.handle_midi(&[0xC9, 25, 0x99, 69, 100]);
+        assert!(render(&mut synth, 0.001).iter().all(|&sample| sample == 0.0));
+        synth.handle_midi(&[0x99, 35, 100]);
+        assert!(render(&mut synth, 0.001).iter().any(|&sample| sample != 0.0));
+        render(&mut synth, 0.001);
+        synth.handle_midi(&[0xC9, 26, 0x99, 69, 100]);
+        assert!(render(&mut synth, 0.001).iter().any(|&sample| sample != 0.0));
+
+        // Programs without a preset keep their instrument
+        synth.handle_midi(&[0xC0, 1, 0x90, 61, 100]);
+        assert!(render(&mut synth, 0.1).iter().any(|&sample| sample != 0.0));
+    }
+
+    #[test]
+    fn band_limited() {
+        // Amplitude at `frequency` in Hz, through a Hann window
+        let amplitude = |samples: &[f64], frequency: f64| {
+            let (mut re, mut im) = (0.0, 0.0);
+            for (n, sample) in samples.iter().enumerate() {
+                let window = 0.5 - 0.5 * (std::f64::consts::TAU * n as f64 / samples.len() as f64).cos();
+                let phase = std::f64::consts::TAU * frequency * n as f64 / DEFAULT_RATE as f64;
+                re += window * sample * phase.cos();
+                im -= window * sample * phase.sin();
+            }
+            re.hypot(im)
+        };
+        let rate = DEFAULT_RATE as f64;
+
+        // Saw and square synths playing A7, whose upper harmonics are past the Nyquist frequency
+        for program in [80, 81] {
+            let mut synth = Synth::new(DEFAULT_RATE, DEFAULT_POLYPHONY, StealPolicy::default());
+            synth.handle_midi(&[0xC0, program, 0x90, 105, 127]);
+            let mut buf = vec![0.0; DEFAULT_RATE as usize / 5];
+            synth.render(&mut buf);
+            let left: Vec<f64> = buf.iter().step_by(2).map(|&sample| f64::from(sample)).collect();
+
+            let fundamental = 440.0 * 2.0f64.powf((105.0 - 69.0) / 12.0);
+            let reference = amplitude(&left, fundamental);
+            assert!(reference > 0.0);
+            for harmonic in 2..=7 {
+                let frequency = fundamental * harmonic as f64;
+                if frequency < rate / 2.0 {
+                    continue;
+                }
+                // Where the harmonic would fold back to
+                let alias = (frequency - rate * (frequency / rate).round()).abs();
+                assert!(amplitude(&left, alias) < reference * 1e-3, "program {program} aliases harmonic {harmonic} to {alias} Hz");
+            }
+        }
+    }
+
+    #[test]
+    fn drum_slots() {
+        let crash = percussion::drum(49).unwrap();
+        let mut drums = [percussion::DrumNote::default(); DRUM_NOTE_COUNT * 2];
+        for hit in 0..DRUM_NOTE_COUNT * 3 {
+            percussion::start(&mut drums, DRUM_NOTE_COUNT, 49, 8192, crash);
+            assert_eq!(drums.iter().filter(|drum_note| drum_note.sample_time == 1).count(), 1, "hit {hit} was dropped");
+            for drum_note in &mut drums {
+                drum_note.next_sample(1.0 / DEFAULT_RATE as f64);
+            }
+        }
+        assert!(drums.iter().all(|drum_note| drum_note.sample_time != 0));
+    }
+
+    #[test]
+    fn general_midi_bank() {
+        assert_eq!(instrument::INSTRUMENTS.len(), 128);
+        let patches: Vec<String> = instrument::INSTRUMENTS.iter().map(|instrument| format!("{instrument:?}")).collect();
+        for (program, patch) in patches.iter().enumerate() {
+            assert!(!patches[..program].contains(patch), "program {program} is a copy of an earlier one");
+
+            let mut synth = Synth::new(DEFAULT_RATE, DEFAULT_POLYPHONY, StealPolicy::default());
+            synth.handle_midi(&[0xC0, program as u8, 0x90, 60, 100]);
+            let mut buf = vec![0.0; DEFAULT_RATE as usize / 2];
+            synth.render(&mut buf);
+            let power = buf.iter().map(|sample| sample * sample).sum::<f32>() / buf.len() as f32;
+            assert!(power > 1e-6, "program {program} is silent");
+            assert!(buf.iter().all(|sample| sample.abs() < 1.0), "program {program} clips");
+        }
+    }
+}
@@ -1,81 +1,101 @@
-use std::{time::{Duration, Instant}, io::Read, os::unix::prelude::AsRawFd, env};
+use std::{io, os::unix::prelude::AsRawFd, time::Duration};
 
-use psimple::Simple;
-use pulse::sample::CHANNELS_MAX;
+use player::{Bank, SoundFont, StealPolicy, Synth, DEFAULT_POLYPHONY, DEFAULT_RATE};
 
-mod sys {
-    extern "C" {
-        pub(super) fn opt_sin(x: f64) -> f64;
-    }
-}
-
-fn opt_sin(x: f64) -> f64 {
-    unsafe { sys::opt_sin(x) }
-}
-
-const NOTE_COUNT: usize = 8;
-const CHANNEL_COUNT: usize = 16;
-const RATE: u32 = 44100;
-const SAMPLE_DT: f64 = 1.0 / RATE as f64;
 const BUFSIZE: usize = 128;
+/// Longest time notes may ring on after the end of a rendered file, in seconds
+const TAIL_LENGTH: u64 = 10;
 
-#[derive(Debug, Default, Clone, Copy)]
-struct Note {
-    note: u8,
-    amp: u32,
-    freq: f64,
-    /// Zero indicates this not is not in use
-    sample_time: u64,
-    /// Zero indicates that this note is ongoing
-    /// Positive value indicates that this note should stop at that sample time
-    stop_time: u64,
-    /// Current sin() argument. Keeping track of this helps pitch-bending to not sound bad.
-    current_parameter: f64,
-    instrument: &'static Instrument,
-}
+mod output;
+mod wav;
 
-#[derive(Debug, Clone, Copy)]
-struct Envelope {
-    attack: u64,
-    decay: u64,
-    sustain: f64,
-    release: u64,
+struct Options {
+    polyphony: usize,
+    steal_policy: StealPolicy,
+    output: output::OutputKind,
+    /// Samples per second
+    rate: u32,
+    format: output::SampleFormat,
+    /// TOML patch file to load instruments from
+    patches: Option<String>,
+    /// SoundFont 2 file whose presets play instead of the instruments
+    soundfont: Option<String>,
+    /// MIDI file to play, instead of reading MIDI from stdin
+    filename: Option<String>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-struct NoteShouldStop;
+fn usage() -> ! {
+    eprintln!(
+        "Usage: {} [--polyphony N] [--steal oldest|quietest|releasing] [--output pulse|raw:PATH|wav:PATH|null] \
+         [--rate HZ] [--format s16|f32] [--patches PATCHES.toml] [--soundfont FONT.sf2] [song.mid]\n\
+         Plays a MIDI file, or MIDI from stdin as it arrives, through PulseAudio or another output.\n\
+         Output is stereo at 44100 Hz unless --rate is given, and raw:- writes raw samples to stdout.\n\
+         A MIDI file is rendered to a file output as fast as possible.",
+        std::env::args().next().as_deref().unwrap_or("cargo run"),
+    );
+    std::process::exit(1);
+}
 
-impl Envelope {
-    fn envelope(&self, sample_time: u64, stop_time: u64) -> Result<f64, NoteShouldStop> {
-        Ok(if sample_time < stop_time { // Release
-            let inverse_progress = (stop_time - sample_time) as f64 / self.release as f64;
-            // (1.0 - progress) * self.sustain
-            inverse_progress * self.sustain
-        } else if sample_time < self.attack {
-            sample_time as f64 / self.attack as f64
-        } else if (sample_time - self.attack) < self.decay {
-            let progress = (sample_time - self.attack) as f64 / self.decay as f64;
-            (1.0 - progress) + progress * self.sustain
-        } else if self.sustain <= 0.0001 { // End after decay if sustain ~= 0
-            return Err(NoteShouldStop);
-        } else { // Normal sustain volume
-            self.sustain
-        })
-    }
+#[cfg(feature = "pulse")]
+fn default_output() -> output::OutputKind {
+    output::OutputKind::Pulse
 }
 
-#[derive(Debug, Clone, Copy)]
-struct Instrument {
-    amplitudes: &'static [f64],
-    full_amplitude: f64,
-    envelope: Envelope,
+/// Built without PulseAudio, so there is nothing to play through
+#[cfg(not(feature = "pulse"))]
+fn default_output() -> output::OutputKind {
+    output::OutputKind::Null
 }
-mod instrument;
 
-impl Default for &'static Instrument {
-    fn default() -> Self {
-        &instrument::INSTRUMENTS[0]
+fn parse_args() -> Options {
+    let mut options = Options {
+        polyphony: DEFAULT_POLYPHONY,
+        steal_policy: StealPolicy::default(),
+        output: default_output(),
+        rate: DEFAULT_RATE,
+        format: output::SampleFormat::default(),
+        patches: None,
+        soundfont: None,
+        filename: None,
+    };
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--polyphony" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                options.polyphony = value.parse().ok().filter(|polyphony| *polyphony > 0).unwrap_or_else(|| usage());
+            }
+            "--steal" => {
+                options.steal_policy = match args.next().as_deref() {
+                    Some("oldest") => StealPolicy::Oldest,
+                    Some("quietest") => StealPolicy::Quietest,
+                    Some("releasing") => StealPolicy::Releasing,
+                    _ => usage(),
+                };
+            }
+            "--output" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                options.output = output::OutputKind::parse(&value).unwrap_or_else(|| usage());
+            }
+            "--rate" => {
+                let value = args.next().unwrap_or_else(|| usage());
+                options.rate = value.parse().ok().filter(|rate| (8000..=192000).contains(rate)).unwrap_or_else(|| usage());
+            }
+            "--format" => {
+                options.format = match args.next().as_deref() {
+                    Some("s16") => output::SampleFormat::S16,
+                    Some("f32") => output::SampleFormat::F32,
+                    _ => usage(),
+                };
+            }
+            "--patches" => options.patches = Some(args.next().unwrap_or_else(|| usage())),
+            "--soundfont" => options.soundfont = Some(args.next().unwrap_or_else(|| usage())),
+            _ if options.filename.is_none() && !arg.starts_with("--") => options.filename = Some(arg),
+            _ => usage(),
+        }
     }
+    options
 }
 
 fn poll_in(input: &impl AsRawFd) -> bool {
@@ -84,9 +104,9 @@ fn poll_in(input: &impl AsRawFd) -> bool {
         events: libc::POLLIN,
         revents: 0,
     };
-    let ret = unsafe {
-        libc::poll(&mut pollfd, 1, 0)
-    };
+    unsafe {
+        libc::poll(&mut pollfd, 1, 0);
+    }
     pollfd.revents & (libc::POLLIN | libc::POLLHUP) != 0
 }
 
@@ -98,166 +118,159 @@ fn read_in(input: &impl AsRawFd, buf: &mut [u8]) -> Result<usize, &'static str>
     ret.try_into().map_err(|_| "Failed to read")
 }
 
-fn main() {
-    let sample_spec = pulse::sample::Spec {
-        format: pulse::sample::Format::S16le,
-        rate: RATE,
-        channels: 1,
-    };
-    
-    let s = Simple::new(
-        None,
-        "midi player",
-        pulse::stream::Direction::Playback,
-        None,
-        "Music",
-        &sample_spec,
-        None,
-        None,
-    ).expect("Failed to connect to pulseaudio.");
+/// Plays MIDI from stdin as it arrives, until it ends.
+fn play_stdin(synth: &mut Synth, output: &mut dyn output::Output) -> io::Result<()> {
+    let stdin = std::io::stdin();
 
-    let mut instruments = [<&Instrument>::default(); CHANNEL_COUNT];
-    let mut notes = [<[Note; NOTE_COUNT]>::default(); CHANNEL_COUNT];
-
-    let mut stdin = std::io::stdin();
-
-    let mut buf = vec![0i16; BUFSIZE];
+    // Interleaved left and right samples
+    let mut buf = vec![0.0; BUFSIZE * 2];
+    let start = std::time::Instant::now();
+    let sample_dt = 1.0 / synth.rate() as f64;
+    let mut frames = 0;
 
     let mut running = true;
-    'main_loop: while running {
+    while running {
         let read_start = std::time::Instant::now();
-        'read_loop: while running && poll_in(&stdin) {
-            let mut buf = [0u8; 3];
-            let bytes_read = read_in(&stdin, &mut buf[..1]).expect("Failed to read");
+        while running && poll_in(&stdin) {
+            let mut input = [0u8; 256];
+            let bytes_read = read_in(&stdin, &mut input).expect("Failed to read");
             if bytes_read == 0 {
                 // EOF
                 running = false;
-                break 'read_loop;
-            }
-            match buf[0] {
-                0x80..=0x8f | 0x90..=0x9f => {
-                    // Note-off or Note-on 
-                    let bytes_read = read_in(&stdin, &mut buf[1..3]).expect("Failed to read");
-                    if bytes_read != 2 {
-                        // EOF
-                        running = false;
-                        break 'read_loop;
-                    }
-                    let channel = (buf[0] & 0x0f) as usize;
-                    if channel == 9 { continue; } // Deal with percussion later
-                    let note = buf[1];
-                    let velocity = buf[2];
-                    let off = matches!(buf[0], 0x80..=0x8f) || velocity == 0;
-                    // println!("channel {channel} {on}, {note}, {velocity}", on = if off {"off"} else {"on"});
-
-                    for note_info in &mut notes[channel] {
-                        if off && note_info.note == note && note_info.sample_time > 0 && note_info.stop_time == 0  {
-                            note_info.stop_time = note_info.sample_time + note_info.instrument.envelope.release;
-                            break;
-                        } else if !off && note_info.sample_time == 0 {
-                            note_info.sample_time = 1;
-                            note_info.stop_time = 0;
-                            note_info.note = note;
-                            note_info.amp = velocity as u32 * 8192 / 0xff;
-                            note_info.freq = 440.0 * (2.0f64).powf((note as f64 - 69.0) as f64 / 12.0);
-                            note_info.instrument = instruments[channel];
-                            break;
-                        }
-                    }
-                }
-                0xC0..=0xCf => {
-                    // Program change
-                    let bytes_read = read_in(&stdin, &mut buf[1..2]).expect("Failed to read");
-                    if bytes_read != 1 {
-                        // EOF
-                        running = false;
-                        break 'read_loop;
-                    }
-                    let channel = (buf[0] & 0x0f) as usize;
-                    if channel == 9 { continue; } // Deal with percussion later
-                    // println!("Channel {channel} program change to {prog}", prog = buf[1]);
-                    instruments[channel] = &instrument::INSTRUMENTS[buf[1] as usize];
-                    // println!("Channel {channel} program change to {prog} ({inst:?})", prog = buf[1], inst=instruments[channel]);
-                }
-                b => {dbg!(b);},
-            };
-        }
-        
-        let mut ampsum = 0;
-        for note in notes.iter().flatten() {
-            if note.sample_time != 0 {
-                ampsum += note.amp;
+                break;
             }
+            synth.handle_midi(&input[..bytes_read]);
         }
 
-        let start = std::time::Instant::now();
-        for i in 0..BUFSIZE {
-        // for i in 0..0 {
-            let mut wav = 0.0;
-
-            for (channel, channel_notes) in notes.iter_mut().enumerate() {
-                for note in channel_notes {
-                    if note.stop_time != 0 && note.sample_time >= note.stop_time {
-                        note.sample_time = 0;
-                    }
-                    if note.sample_time == 0 {
-                        continue;
-                    }
-                    note.sample_time += 1;
+        synth.render(&mut buf);
+        output.write(&buf)?;
 
-                    let envelope = &note.instrument.envelope;
-
-                    let env = match envelope.envelope(note.sample_time, note.stop_time) {
-                        Ok(env) => env,
-                        Err(_) => {
-                            note.sample_time = 0;
-                            continue;
-                        }
-                    };
-
-                    let mut wava = 0.0;
-                    note.current_parameter = note.current_parameter + std::f64::consts::TAU * SAMPLE_DT * note.freq;
-
-                    for (i, amp) in note.instrument.amplitudes.iter().copied().enumerate() {
-                        let parameter = (i + 1) as f64 * note.current_parameter;
-                        wava += amp * parameter.sin();
-                        // wava += amp * opt_sin(parameter);
-                    }
-                    wav += note.amp as f64 * wava * env;
-                }
-            }
-            buf[i] = if ampsum > 32767 {
-                wav * (32767.0 / ampsum as f64)
-            } else {
-                wav
-            } as i16;
+        frames += BUFSIZE as u64;
+        if !output.is_realtime() {
+            // Without a sound card to keep time, keep up with the wall clock instead
+            let deadline = start + Duration::from_secs_f64(frames as f64 * sample_dt);
+            std::thread::sleep(deadline.saturating_duration_since(std::time::Instant::now()));
         }
 
-        // write data to pulse
-        s.write(bytemuck::cast_slice(&buf)).expect("Failed to write audio data");
-        
         let end = std::time::Instant::now();
         let took = end - read_start;
-        let expected = Duration::from_secs_f64(BUFSIZE as f64 * 2.0 * SAMPLE_DT);
+        let expected = Duration::from_secs_f64(BUFSIZE as f64 * 2.0 * sample_dt);
         if took > expected {
             // println!("Segment took too long! {took:?}, expected: {expected:?}");
         }
     }
-    
+    Ok(())
+}
+
+/// Plays a MIDI file as fast as the output takes it, with every event on the exact sample it falls on.
+/// A sound card takes it in real time, while a file takes it as fast as it can be rendered.
+fn play_file(synth: &mut Synth, smf: &midly::Smf, output: &mut dyn output::Output) -> io::Result<()> {
+    let mut buf = vec![0.0; BUFSIZE * 2];
+    let mut frames = 0;
+    let mut render = |synth: &mut Synth, until: u64| -> io::Result<()> {
+        while frames < until {
+            let len = (until - frames).min(BUFSIZE as u64) as usize;
+            synth.render(&mut buf[..len * 2]);
+            output.write(&buf[..len * 2])?;
+            frames += len as u64;
+        }
+        Ok(())
+    };
+
+    let mut end = 0;
+    for (time, _, _, event) in parser::MidiEventIter::new(smf) {
+        end = (time.as_secs_f64() * synth.rate() as f64).round() as u64;
+        render(synth, end)?;
+        if let Some(event) = event.as_live_event() {
+            synth.handle_event(event);
+        }
+    }
+
+    // Let notes ring out, but not forever
+    let tail_end = end + TAIL_LENGTH * synth.rate() as u64;
+    while !synth.is_silent() && end < tail_end {
+        end += BUFSIZE as u64;
+        render(synth, end)?;
+    }
+    Ok(())
+}
+
+fn main() {
+    let options = parse_args();
+    let mut synth = Synth::new(options.rate, options.polyphony, options.steal_policy);
+    let mut bank = match &options.patches {
+        Some(patches) => Bank::load(patches).unwrap_or_else(|err| {
+            eprintln!("Failed to load {patches}: {err}");
+            std::process::exit(1);
+        }),
+        None => Bank::builtin(),
+    };
+    if let Some(soundfont) = &options.soundfont {
+        let font = SoundFont::load(soundfont).unwrap_or_else(|err| {
+            eprintln!("Failed to load {soundfont}: {err}");
+            std::process::exit(1);
+        });
+        bank = bank.with_soundfont(font);
+    }
+    synth.set_bank(bank);
+
+    let mut output = options.output.open(options.rate, options.format).unwrap_or_else(|err| {
+        eprintln!("Failed to open output: {err}");
+        std::process::exit(1);
+    });
+
+    let result = match &options.filename {
+        Some(filename) => {
+            let data = std::fs::read(filename).unwrap_or_else(|err| {
+                eprintln!("Failed to read {filename}: {err}");
+                std::process::exit(1);
+            });
+            let smf = midly::Smf::parse(&data).unwrap_or_else(|err| {
+                eprintln!("Failed to parse {filename}: {err}");
+                std::process::exit(1);
+            });
+            play_file(&mut synth, &smf, output.as_mut())
+        }
+        None => play_stdin(&mut synth, output.as_mut()),
+    };
+    if let Err(err) = result.and_then(|()| output.finish()) {
+        eprintln!("Failed to write audio: {err}");
+        std::process::exit(1);
+    }
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+
     #[test]
-    fn envelope() {
-        let piano = instrument::INSTRUMENTS[0];
-        let envelope = piano.envelope;
-        assert_eq!(envelope.envelope(0, u64::MAX), Ok(0.0));
-        assert_eq!(envelope.envelope(envelope.attack / 2, u64::MAX), Ok(0.5));
-        assert_eq!(envelope.envelope(envelope.attack, u64::MAX), Ok(1.0));
-        assert_eq!(envelope.envelope(envelope.attack + envelope.decay / 2, u64::MAX), Ok(1.0 - (1.0 - envelope.sustain) * 0.5));
-        assert_eq!(envelope.envelope(envelope.attack + envelope.decay, u64::MAX), Ok(envelope.sustain));
+    fn render_wav() {
+        use midly::{Header, Format, Timing, TrackEvent, TrackEventKind, MidiMessage, MetaMessage};
+        let note = |delta: u32, message| TrackEvent {
+            delta: delta.into(),
+            kind: TrackEventKind::Midi { channel: 0.into(), message },
+        };
+        let mut smf = midly::Smf::new(Header::new(Format::SingleTrack, Timing::Metrical(480.into())));
+        smf.tracks.push(vec![
+            note(0, MidiMessage::NoteOn { key: 69.into(), vel: 100.into() }),
+            // One beat at 120 bpm
+            note(480, MidiMessage::NoteOff { key: 69.into(), vel: 0.into() }),
+            TrackEvent { delta: 0.into(), kind: TrackEventKind::Meta(MetaMessage::EndOfTrack) },
+        ]);
+
+        let mut synth = Synth::new(DEFAULT_RATE, DEFAULT_POLYPHONY, StealPolicy::default());
+        let mut out = wav::WavWriter::new(io::Cursor::new(Vec::new()), DEFAULT_RATE, 2, output::SampleFormat::S16).unwrap();
+        play_file(&mut synth, &smf, &mut out).unwrap();
+        let wav = out.finish().unwrap().into_inner();
+
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(u32::from_le_bytes(wav[4..8].try_into().unwrap()) as usize, wav.len() - 8);
+        assert_eq!(u32::from_le_bytes(wav[40..44].try_into().unwrap()) as usize, wav.len() - 44);
+        // The note plays for half a second, then rings out until its release ends
+        let frames = (wav.len() - 44) / 4;
+        assert!(frames > DEFAULT_RATE as usize / 2 && frames < DEFAULT_RATE as usize, "{frames} frames");
+        assert!(synth.is_silent());
     }
 }
+
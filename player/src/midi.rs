@@ -0,0 +1,169 @@
+//! Incremental parser for a MIDI 1.0 byte stream, as it arrives on stdin.
+
+/// Longest SysEx message that is kept, including F0 and F7. Longer ones are dropped.
+const SYSEX_MAX: usize = 1024;
+
+#[derive(Debug, Default)]
+pub(super) struct MidiParser {
+    /// The message being read, starting with its status byte
+    message: [u8; 3],
+    /// How many bytes of `message` have been read, or zero if there is no status to read data for
+    len: usize,
+    /// Whether the status in `message` may be reused by the following data bytes
+    running_status: bool,
+    /// Whether a SysEx message is being read
+    in_sysex: bool,
+    /// The SysEx message being read, from F0 on
+    sysex: Vec<u8>,
+    /// A real-time message, which may come between any two bytes
+    realtime: [u8; 1],
+}
+
+/// How many data bytes follow a status byte
+fn data_len(status: u8) -> usize {
+    match status {
+        0x80..=0xBF | 0xE0..=0xEF => 2,
+        0xC0..=0xDF => 1,
+        // Song position pointer
+        0xF2 => 2,
+        // MIDI time code quarter frame, song select
+        0xF1 | 0xF3 => 1,
+        _ => 0,
+    }
+}
+
+impl MidiParser {
+    pub(super) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads one byte, returning the message it completes, if any.
+    /// Messages are returned whole, status byte first, and a SysEx message includes the F0 and F7.
+    /// Data bytes without a status to apply to are ignored.
+    pub(super) fn push(&mut self, byte: u8) -> Option<&[u8]> {
+        match byte {
+            // Real-time messages do not interrupt anything else
+            0xF8..=0xFF => {
+                self.realtime = [byte];
+                Some(&self.realtime)
+            }
+            0xF0 => {
+                self.len = 0;
+                self.running_status = false;
+                self.in_sysex = true;
+                self.sysex.clear();
+                self.sysex.push(byte);
+                None
+            }
+            0xF7 => {
+                if !self.in_sysex || self.sysex.len() >= SYSEX_MAX {
+                    self.in_sysex = false;
+                    return None;
+                }
+                self.in_sysex = false;
+                self.sysex.push(byte);
+                Some(&self.sysex)
+            }
+            0x80..=0xEF | 0xF1..=0xF6 => {
+                // Any other status byte ends an unfinished SysEx, which is dropped
+                self.in_sysex = false;
+                self.message[0] = byte;
+                self.len = 1;
+                self.running_status = byte < 0xF0;
+                self.complete()
+            }
+            _ => {
+                if self.in_sysex {
+                    if self.sysex.len() < SYSEX_MAX {
+                        self.sysex.push(byte);
+                    }
+                    return None;
+                }
+                if self.len == 0 {
+                    return None;
+                }
+                self.message[self.len] = byte;
+                self.len += 1;
+                self.complete()
+            }
+        }
+    }
+
+    /// Returns the message if all of its data bytes have been read, and gets ready for the next one.
+    fn complete(&mut self) -> Option<&[u8]> {
+        let len = 1 + data_len(self.message[0]);
+        if self.len < len {
+            return None;
+        }
+        self.len = if self.running_status { 1 } else { 0 };
+        Some(&self.message[..len])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(bytes: &[u8]) -> Vec<Vec<u8>> {
+        let mut parser = MidiParser::new();
+        bytes.iter().filter_map(|&byte| parser.push(byte).map(<[u8]>::to_vec)).collect()
+    }
+
+    #[test]
+    fn data_lengths() {
+        assert_eq!(
+            parse(&[0x90, 60, 100, 0xC1, 5, 0xD2, 40, 0xE3, 0, 64, 0xF1, 3, 0xF2, 1, 2, 0xF6]),
+            [vec![0x90, 60, 100], vec![0xC1, 5], vec![0xD2, 40], vec![0xE3, 0, 64], vec![0xF1, 3], vec![0xF2, 1, 2], vec![0xF6]],
+        );
+    }
+
+    #[test]
+    fn running_status() {
+        assert_eq!(
+            parse(&[0x90, 60, 100, 64, 100, 60, 0, 0xC0, 1, 2]),
+            [vec![0x90, 60, 100], vec![0x90, 64, 100], vec![0x90, 60, 0], vec![0xC0, 1], vec![0xC0, 2]],
+        );
+        // System common messages cancel running status
+        assert_eq!(parse(&[0xB0, 7, 100, 0xF3, 1, 10, 20]), [vec![0xB0, 7, 100], vec![0xF3, 1]]);
+        // Data without any status is ignored
+        assert_eq!(parse(&[1, 2, 0x80, 60, 0]), [vec![0x80, 60, 0]]);
+    }
+
+    #[test]
+    fn realtime_anywhere() {
+        assert_eq!(
+            parse(&[0x90, 0xF8, 60, 0xFE, 100, 62, 0xFA, 100]),
+            [vec![0xF8], vec![0xFE], vec![0x90, 60, 100], vec![0xFA], vec![0x90, 62, 100]],
+        );
+    }
+
+    #[test]
+    fn sysex() {
+        let mut parser = MidiParser::new();
+        // Split across reads, with a real-time message in the middle
+        for &byte in &[0xF0, 0x7E, 0x7F] {
+            assert_eq!(parser.push(byte), None);
+        }
+        assert_eq!(parser.push(0xF8), Some(&[0xF8][..]));
+        for &byte in &[0x09, 0x01] {
+            assert_eq!(parser.push(byte), None);
+        }
+        assert_eq!(parser.push(0xF7), Some(&[0xF0, 0x7E, 0x7F, 0x09, 0x01, 0xF7][..]));
+        // SysEx cancels running status
+        assert_eq!(parser.push(0x90), None);
+        assert_eq!(parser.push(60), None);
+        assert_eq!(parser.push(100), Some(&[0x90, 60, 100][..]));
+        assert_eq!(parser.push(0xF0), None);
+        assert_eq!(parser.push(0xF7), Some(&[0xF0, 0xF7][..]));
+        assert_eq!(parser.push(60), None);
+        assert_eq!(parser.push(100), None);
+
+        // Unterminated SysEx is dropped when the next status comes
+        assert_eq!(parse(&[0xF0, 1, 2, 0x80, 60, 0, 0xF7]), [vec![0x80, 60, 0]]);
+        // Too long to keep
+        let mut long = vec![0xF0];
+        long.resize(1 + SYSEX_MAX, 0);
+        long.extend([0xF7, 0xC0, 1]);
+        assert_eq!(parse(&long), [vec![0xC0, 1]]);
+    }
+}
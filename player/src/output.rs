@@ -0,0 +1,172 @@
+//! Where rendered audio goes: a sound server, a file, or nowhere.
+
+use std::{fs::File, io::{self, BufWriter, Write}};
+
+use super::wav::WavWriter;
+
+/// How samples are stored in the output
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(super) enum SampleFormat {
+    /// Signed 16-bit little-endian
+    #[default]
+    S16,
+    /// 32-bit little-endian float
+    F32,
+}
+
+impl SampleFormat {
+    /// Bytes per sample
+    pub(super) fn sample_len(self) -> usize {
+        match self {
+            SampleFormat::S16 => 2,
+            SampleFormat::F32 => 4,
+        }
+    }
+
+    /// Appends samples from -1.0 to 1.0 to `bytes`.
+    pub(super) fn encode(self, samples: &[f32], bytes: &mut Vec<u8>) {
+        match self {
+            SampleFormat::S16 => {
+                bytes.extend(samples.iter().flat_map(|sample| ((sample * 32767.0) as i16).to_le_bytes()));
+            }
+            SampleFormat::F32 => bytes.extend(samples.iter().flat_map(|sample| sample.to_le_bytes())),
+        }
+    }
+}
+
+/// Takes interleaved stereo samples from -1.0 to 1.0, as they are rendered.
+pub(super) trait Output {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()>;
+
+    /// Whether `write` blocks to keep pace with playback, like a sound card does.
+    /// Otherwise, live input has to be paced by the wall clock instead.
+    fn is_realtime(&self) -> bool {
+        false
+    }
+
+    /// Flushes anything buffered, and fills in headers that depend on the length.
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Which output to use, as given to `--output`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(super) enum OutputKind {
+    #[cfg(feature = "pulse")]
+    Pulse,
+    /// Samples with no header, to a file or `-` for stdout
+    Raw(String),
+    Wav(String),
+    Null,
+}
+
+impl OutputKind {
+    /// Parses `pulse`, `raw:PATH`, `wav:PATH` or `null`.
+    pub(super) fn parse(s: &str) -> Option<Self> {
+        match s {
+            #[cfg(feature = "pulse")]
+            "pulse" => Some(OutputKind::Pulse),
+            "null" => Some(OutputKind::Null),
+            _ => {
+                let (kind, path) = s.split_once(':')?;
+                match kind {
+                    "raw" => Some(OutputKind::Raw(path.to_owned())),
+                    "wav" => Some(OutputKind::Wav(path.to_owned())),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    pub(super) fn open(&self, rate: u32, format: SampleFormat) -> io::Result<Box<dyn Output>> {
+        Ok(match self {
+            #[cfg(feature = "pulse")]
+            OutputKind::Pulse => Box::new(Pulse::new(rate, format)?),
+            OutputKind::Raw(path) if path == "-" => Box::new(Raw(BufWriter::new(io::stdout()), format)),
+            OutputKind::Raw(path) => Box::new(Raw(BufWriter::new(File::create(path)?), format)),
+            OutputKind::Wav(path) => Box::new(WavWriter::new(BufWriter::new(File::create(path)?), rate, 2, format)?),
+            OutputKind::Null => Box::new(Null),
+        })
+    }
+}
+
+#[cfg(feature = "pulse")]
+pub(super) struct Pulse(psimple::Simple, SampleFormat);
+
+#[cfg(feature = "pulse")]
+impl Pulse {
+    pub(super) fn new(rate: u32, format: SampleFormat) -> io::Result<Self> {
+        let sample_spec = pulse::sample::Spec {
+            format: match format {
+                SampleFormat::S16 => pulse::sample::Format::S16le,
+                SampleFormat::F32 => pulse::sample::Format::F32le,
+            },
+            rate,
+            channels: 2,
+        };
+
+        psimple::Simple::new(
+            None,
+            "midi player",
+            pulse::stream::Direction::Playback,
+            None,
+            "Music",
+            &sample_spec,
+            None,
+            None,
+        )
+        .map(|simple| Pulse(simple, format))
+        .map_err(|err| io::Error::other(format!("Failed to connect to pulseaudio: {err}")))
+    }
+}
+
+#[cfg(feature = "pulse")]
+impl Output for Pulse {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(samples.len() * self.1.sample_len());
+        self.1.encode(samples, &mut bytes);
+        self.0.write(&bytes).map_err(|err| io::Error::other(format!("{err}")))
+    }
+
+    fn is_realtime(&self) -> bool {
+        true
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        // Play out what is still buffered before going away
+        self.0.drain().map_err(|err| io::Error::other(format!("{err}")))
+    }
+}
+
+pub(super) struct Raw<W: Write>(W, SampleFormat);
+
+impl<W: Write> Output for Raw<W> {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(samples.len() * self.1.sample_len());
+        self.1.encode(samples, &mut bytes);
+        self.0.write_all(&bytes)
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: Write + io::Seek> Output for WavWriter<W> {
+    fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        WavWriter::write(self, samples)
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        WavWriter::finish(*self).map(drop)
+    }
+}
+
+pub(super) struct Null;
+
+impl Output for Null {
+    fn write(&mut self, _samples: &[f32]) -> io::Result<()> {
+        Ok(())
+    }
+}
@@ -0,0 +1,370 @@
+//! Synthesized General MIDI percussion, for channel 10.
+//!
+//! Every drum is a "body" of sine partials whose pitch sweeps down from `start_freq` to
+//! `end_freq`, plus a burst of band-limited noise. Both parts decay exponentially.
+
+/// Lowest key in the General MIDI percussion map (Acoustic Bass Drum)
+const FIRST_KEY: u8 = 35;
+/// Time constant of the fade-out when a drum is cut off by another in its choke group, in seconds
+const CHOKE_TIME: f64 = 0.005;
+
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Drum {
+    /// Frequency ratio and amplitude of each partial of the body
+    partials: &'static [(f64, f64)],
+    /// Body pitch at the start of the hit, in Hz
+    start_freq: f64,
+    /// Body pitch that the sweep settles at, in Hz
+    end_freq: f64,
+    /// Time constant of the pitch sweep, in seconds
+    sweep: f64,
+    /// Time constant of the body's decay, in seconds
+    tone_decay: f64,
+    noise: f64,
+    /// Time constant of the noise's decay, in seconds
+    noise_decay: f64,
+    /// Lower and upper cutoff of the noise, in Hz
+    noise_band: (f64, f64),
+    /// Nonzero if playing this drum cuts off other drums in the same group (e.g. open hi-hat by closed)
+    choke_group: u8,
+}
+
+impl Drum {
+    const SILENT: Drum = Drum {
+        partials: &[(1.0, 1.0)],
+        start_freq: 0.0,
+        end_freq: 0.0,
+        sweep: 0.01,
+        tone_decay: 0.0,
+        noise: 0.0,
+        noise_decay: 0.0,
+        noise_band: (20.0, 20000.0),
+        choke_group: 0,
+    };
+
+    /// How long until the drum is inaudible (about -50dB), in seconds
+    fn length(&self) -> f64 {
+        6.0 * self.tone_decay.max(self.noise_decay)
+    }
+}
+
+const fn kick(freq: f64, decay: f64) -> Drum {
+    Drum {
+        start_freq: freq * 3.0,
+        end_freq: freq,
+        sweep: 0.02,
+        tone_decay: decay,
+        noise: 0.15,
+        noise_decay: 0.005,
+        noise_band: (20.0, 3000.0),
+        ..Drum::SILENT
+    }
+}
+
+const fn snare(noise: f64, noise_decay: f64) -> Drum {
+    Drum {
+        partials: &[(1.0, 0.6), (1.6, 0.3)],
+        start_freq: 260.0,
+        end_freq: 180.0,
+        sweep: 0.01,
+        tone_decay: 0.05,
+        noise,
+        noise_decay,
+        noise_band: (1000.0, 9000.0),
+        ..Drum::SILENT
+    }
+}
+
+const fn tom(freq: f64) -> Drum {
+    Drum {
+        start_freq: freq * 1.5,
+        end_freq: freq,
+        sweep: 0.05,
+        tone_decay: 0.18,
+        noise: 0.1,
+        noise_decay: 0.01,
+        noise_band: (100.0, 4000.0),
+        ..Drum::SILENT
+    }
+}
+
+const fn cymbal(noise_decay: f64, low_cut: f64) -> Drum {
+    Drum {
+        // Inharmonic partials give the metallic ring
+        partials: &[(1.0, 0.1), (1.48, 0.08), (2.17, 0.08), (2.93, 0.06)],
+        start_freq: 430.0,
+        end_freq: 430.0,
+        tone_decay: noise_decay * 0.5,
+        noise: 0.7,
+        noise_decay,
+        noise_band: (low_cut, 16000.0),
+        ..Drum::SILENT
+    }
+}
+
+const fn hi_hat(noise_decay: f64) -> Drum {
+    Drum {
+        partials: &[(1.0, 0.05), (1.34, 0.05), (1.93, 0.05)],
+        start_freq: 3200.0,
+        end_freq: 3200.0,
+        tone_decay: noise_decay,
+        noise: 0.6,
+        noise_decay,
+        noise_band: (7000.0, 16000.0),
+        choke_group: 1,
+        ..Drum::SILENT
+    }
+}
+
+const fn hand_drum(freq: f64, decay: f64) -> Drum {
+    Drum {
+        partials: &[(1.0, 1.0), (1.5, 0.2)],
+        start_freq: freq * 1.2,
+        end_freq: freq,
+        sweep: 0.01,
+        tone_decay: decay,
+        noise: 0.1,
+        noise_decay: 0.005,
+        noise_band: (500.0, 6000.0),
+        ..Drum::SILENT
+    }
+}
+
+const fn block(freq: f64, decay: f64) -> Drum {
+    Drum {
+        partials: &[(1.0, 1.0), (2.7, 0.3)],
+        start_freq: freq,
+        end_freq: freq,
+        tone_decay: decay,
+        ..Drum::SILENT
+    }
+}
+
+const fn shaker(noise_decay: f64, low_cut: f64) -> Drum {
+    Drum {
+        partials: &[],
+        noise: 0.5,
+        noise_decay,
+        noise_band: (low_cut, 14000.0),
+        ..Drum::SILENT
+    }
+}
+
+const fn whistle(decay: f64) -> Drum {
+    Drum {
+        start_freq: 2500.0,
+        end_freq: 2400.0,
+        sweep: 0.05,
+        tone_decay: decay,
+        noise: 0.05,
+        noise_decay: decay,
+        noise_band: (2000.0, 3000.0),
+        ..Drum::SILENT
+    }
+}
+
+const fn cuica(start_freq: f64, end_freq: f64) -> Drum {
+    Drum {
+        partials: &[(1.0, 1.0), (2.0, 0.3)],
+        start_freq,
+        end_freq,
+        sweep: 0.08,
+        tone_decay: 0.12,
+        ..Drum::SILENT
+    }
+}
+
+const fn triangle(decay: f64) -> Drum {
+    Drum {
+        partials: &[(1.0, 0.6), (2.76, 0.3), (5.40, 0.2)],
+        start_freq: 1800.0,
+        end_freq: 1800.0,
+        tone_decay: decay,
+        choke_group: 2,
+        ..Drum::SILENT
+    }
+}
+
+/// Keys 35 to 81 of the General MIDI percussion map
+static DRUMS: [Drum; 47] = [
+    kick(50.0, 0.25), // 35 Acoustic Bass Drum
+    kick(60.0, 0.2), // 36 Bass Drum 1
+    block(1800.0, 0.02), // 37 Side Stick
+    snare(0.7, 0.12), // 38 Acoustic Snare
+    Drum { noise_band: (800.0, 5000.0), ..shaker(0.06, 800.0) }, // 39 Hand Clap
+    snare(0.9, 0.08), // 40 Electric Snare
+    tom(80.0), // 41 Low Floor Tom
+    hi_hat(0.04), // 42 Closed Hi-Hat
+    tom(100.0), // 43 High Floor Tom
+    hi_hat(0.025), // 44 Pedal Hi-Hat
+    tom(120.0), // 45 Low Tom
+    hi_hat(0.3), // 46 Open Hi-Hat
+    tom(140.0), // 47 Low-Mid Tom
+    tom(165.0), // 48 Hi-Mid Tom
+    cymbal(0.6, 4000.0), // 49 Crash Cymbal 1
+    tom(195.0), // 50 High Tom
+    cymbal(0.4, 6000.0), // 51 Ride Cymbal 1
+    cymbal(0.5, 2500.0), // 52 Chinese Cymbal
+    Drum { noise: 0.2, ..triangle(0.5) }, // 53 Ride Bell
+    Drum { partials: &[(1.0, 0.2), (1.7, 0.2)], start_freq: 5000.0, end_freq: 5000.0, tone_decay: 0.08, ..shaker(0.15, 5000.0) }, // 54 Tambourine
+    cymbal(0.3, 5000.0), // 55 Splash Cymbal
+    Drum { partials: &[(1.0, 0.7), (1.48, 0.5)], ..block(560.0, 0.08) }, // 56 Cowbell
+    cymbal(0.7, 3500.0), // 57 Crash Cymbal 2
+    shaker(0.4, 2000.0), // 58 Vibraslap
+    cymbal(0.45, 7000.0), // 59 Ride Cymbal 2
+    hand_drum(400.0, 0.08), // 60 Hi Bongo
+    hand_drum(300.0, 0.1), // 61 Low Bongo
+    hand_drum(330.0, 0.04), // 62 Mute Hi Conga
+    hand_drum(320.0, 0.15), // 63 Open Hi Conga
+    hand_drum(220.0, 0.18), // 64 Low Conga
+    Drum { noise: 0.3, noise_decay: 0.05, ..hand_drum(420.0, 0.15) }, // 65 High Timbale
+    Drum { noise: 0.3, noise_decay: 0.05, ..hand_drum(300.0, 0.18) }, // 66 Low Timbale
+    Drum { partials: &[(1.0, 0.7), (2.4, 0.3)], ..block(900.0, 0.15) }, // 67 High Agogo
+    Drum { partials: &[(1.0, 0.7), (2.4, 0.3)], ..block(650.0, 0.15) }, // 68 Low Agogo
+    shaker(0.05, 6000.0), // 69 Cabasa
+    shaker(0.04, 8000.0), // 70 Maracas
+    whistle(0.06), // 71 Short Whistle
+    whistle(0.3), // 72 Long Whistle
+    shaker(0.04, 1500.0), // 73 Short Guiro
+    shaker(0.2, 1500.0), // 74 Long Guiro
+    block(2500.0, 0.03), // 75 Claves
+    block(1200.0, 0.04), // 76 Hi Wood Block
+    block(900.0, 0.05), // 77 Low Wood Block
+    cuica(800.0, 500.0), // 78 Mute Cuica
+    cuica(500.0, 900.0), // 79 Open Cuica
+    triangle(0.08), // 80 Mute Triangle
+    triangle(0.8), // 81 Open Triangle
+];
+
+/// The drum sound for a key on the percussion channel, if General MIDI defines one.
+pub(super) fn drum(key: u8) -> Option<&'static Drum> {
+    DRUMS.get(usize::from(key.checked_sub(FIRST_KEY)?))
+}
+
+/// Source of white noise, with a band-pass made of two one-pole filters.
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct Noise {
+    state: u32,
+    low: f64,
+    high: f64,
+}
+
+impl Noise {
+    /// Any nonzero seed will do, but differing ones keep simultaneous notes from phasing
+    pub(super) fn new(seed: u32) -> Self {
+        Noise { state: seed.max(1), ..Noise::default() }
+    }
+
+    /// White noise band-passed between two frequencies in Hz
+    pub(super) fn sample(&mut self, (low_cut, high_cut): (f64, f64), sample_dt: f64) -> f64 {
+        // xorshift32
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        let white = self.state as f64 / u32::MAX as f64 * 2.0 - 1.0;
+
+        let coefficient = |cutoff: f64| 1.0 - (-std::f64::consts::TAU * cutoff * sample_dt).exp();
+        self.high += coefficient(high_cut) * (white - self.high);
+        self.low += coefficient(low_cut) * (self.high - self.low);
+        self.high - self.low
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub(super) struct DrumNote {
+    pub(super) amp: u32,
+    /// Zero indicates this drum is not in use
+    pub(super) sample_time: u64,
+    /// Current sin() argument of the body
+    current_parameter: f64,
+    /// Nonzero if another drum cut this one off, at that sample time
+    choke_time: u64,
+    drum: Option<&'static Drum>,
+    noise: Noise,
+}
+
+impl DrumNote {
+    pub(super) fn start(&mut self, key: u8, amp: u32, drum: &'static Drum) {
+        *self = DrumNote {
+            amp,
+            sample_time: 1,
+            current_parameter: 0.0,
+            choke_time: 0,
+            drum: Some(drum),
+            noise: Noise::new(0x9E37_79B9 ^ u32::from(key)),
+        };
+    }
+
+    /// Cuts this drum off, if it is playing and `by` is in the same choke group.
+    pub(super) fn choke(&mut self, by: &Drum) {
+        match self.drum {
+            Some(drum) if self.sample_time != 0 && by.choke_group != 0 && drum.choke_group == by.choke_group => {
+                self.choke_time = self.sample_time;
+            }
+            _ => {}
+        }
+    }
+
+    /// Cuts this drum off, if it is playing.
+    pub(super) fn fade_out(&mut self) {
+        if self.sample_time != 0 && self.choke_time == 0 {
+            self.choke_time = self.sample_time;
+        }
+    }
+
+    /// The next sample of this drum, or zero if it is not playing.
+    /// `sample_dt` is the time between samples, in seconds.
+    pub(super) fn next_sample(&mut self, sample_dt: f64) -> f64 {
+        let Some(drum) = self.drum.filter(|_| self.sample_time != 0) else {
+            return 0.0;
+        };
+        let t = self.sample_time as f64 * sample_dt;
+        // A choked drum fades out quickly, rather than stopping with a click
+        let choke = match self.choke_time {
+            0 => 1.0,
+            choke_time => (-((self.sample_time - choke_time) as f64 * sample_dt) / CHOKE_TIME).exp(),
+        };
+        if t > drum.length() || choke < 0.001 {
+            self.sample_time = 0;
+            return 0.0;
+        }
+        self.sample_time += 1;
+
+        let freq = drum.end_freq + (drum.start_freq - drum.end_freq) * (-t / drum.sweep).exp();
+        self.current_parameter += std::f64::consts::TAU * sample_dt * freq;
+        let mut tone = 0.0;
+        for &(ratio, amp) in drum.partials {
+            tone += amp * (ratio * self.current_parameter).sin();
+        }
+        tone *= (-t / drum.tone_decay).exp();
+
+        let noise = if drum.noise > 0.0 {
+            drum.noise * self.noise.sample(drum.noise_band, sample_dt) * (-t / drum.noise_decay).exp()
+        } else {
+            0.0
+        };
+
+        self.amp as f64 * (tone + noise) * choke
+    }
+
+    fn is_sounding(&self) -> bool {
+        self.sample_time != 0 && self.choke_time == 0
+    }
+}
+
+/// Starts a drum in `drums`, which has room for `sounding` drums plus as many fading out.
+/// Once `sounding` drums are playing, the one that started first fades out to make room.
+pub(super) fn start(drums: &mut [DrumNote], sounding: usize, key: u8, amp: u32, drum: &'static Drum) {
+    if drums.iter().filter(|drum_note| drum_note.is_sounding()).count() >= sounding {
+        if let Some(oldest) = drums.iter_mut().filter(|drum_note| drum_note.is_sounding()).max_by_key(|drum_note| drum_note.sample_time) {
+            oldest.fade_out();
+        }
+    }
+    // Only drums fading out can fill the spare slots; cut short the one furthest along
+    let slot = drums.iter().position(|drum_note| drum_note.sample_time == 0).or_else(|| {
+        (0..drums.len()).max_by_key(|&i| drums[i].sample_time.saturating_sub(drums[i].choke_time))
+    });
+    if let Some(slot) = slot {
+        drums[slot].start(key, amp, drum);
+    }
+}
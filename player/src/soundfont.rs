@@ -0,0 +1,624 @@
+//! SoundFont 2 files: sampled instruments, played in place of the built-in ones.
+//!
+//! Presets, instruments and their zones are flattened when the file is loaded, so that each
+//! preset is a list of zones that each play one sample over a range of keys and velocities.
+//! Sample loops, tuning, attenuation and the volume envelope are used. Delay and hold in the
+//! volume envelope, the modulation envelope, LFOs, filters, modulators and pan are not.
+
+use std::{fmt, io, ops::RangeInclusive, path::Path, sync::Arc};
+
+use super::Envelope;
+
+/// Bank that percussion presets are in
+const PERCUSSION_BANK: u16 = 128;
+
+// Generators that are used, by their SF2 number
+const START_OFFSET: usize = 0;
+const END_OFFSET: usize = 1;
+const START_LOOP_OFFSET: usize = 2;
+const END_LOOP_OFFSET: usize = 3;
+const START_COARSE_OFFSET: usize = 4;
+const END_COARSE_OFFSET: usize = 12;
+const ATTACK_VOL_ENV: usize = 34;
+const DECAY_VOL_ENV: usize = 36;
+const SUSTAIN_VOL_ENV: usize = 37;
+const RELEASE_VOL_ENV: usize = 38;
+const INSTRUMENT: usize = 41;
+const KEY_RANGE: usize = 43;
+const VEL_RANGE: usize = 44;
+const START_LOOP_COARSE_OFFSET: usize = 45;
+const INITIAL_ATTENUATION: usize = 48;
+const END_LOOP_COARSE_OFFSET: usize = 50;
+const COARSE_TUNE: usize = 51;
+const FINE_TUNE: usize = 52;
+const SAMPLE_ID: usize = 53;
+const SAMPLE_MODES: usize = 54;
+const SCALE_TUNING: usize = 56;
+const OVERRIDING_ROOT_KEY: usize = 58;
+const GENERATOR_COUNT: usize = 61;
+
+/// Generators that presets add to those of their instruments, rather than leaving alone
+const ADDITIVE: [usize; 8] = [
+    ATTACK_VOL_ENV, DECAY_VOL_ENV, SUSTAIN_VOL_ENV, RELEASE_VOL_ENV, INITIAL_ATTENUATION, COARSE_TUNE, FINE_TUNE, SCALE_TUNING,
+];
+
+/// Sampled presets from a SoundFont 2 file
+#[derive(Debug)]
+pub struct SoundFont {
+    presets: Vec<Preset>,
+}
+
+#[derive(Debug)]
+pub enum SoundFontError {
+    Io(io::Error),
+    /// The file is not a SoundFont 2 file, or is damaged
+    Invalid(&'static str),
+}
+
+impl fmt::Display for SoundFontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SoundFontError::Io(err) => write!(f, "cannot read soundfont: {err}"),
+            SoundFontError::Invalid(reason) => write!(f, "invalid soundfont: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SoundFontError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SoundFontError::Io(err) => Some(err),
+            SoundFontError::Invalid(_) => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct Preset {
+    bank: u16,
+    program: u16,
+    zones: Vec<Zone>,
+}
+
+/// A preset, with the program number it is selected by
+type Program = (u8, Preset);
+
+impl Preset {
+    /// The zones that a note plays, with their indices, of which there may be several layered or
+    /// none at all
+    pub(super) fn zones(&self, key: u8, velocity: u8) -> impl Iterator<Item = (usize, &Zone)> {
+        self.zones.iter().enumerate().filter(move |(_, zone)| zone.keys.contains(&key) && zone.velocities.contains(&velocity))
+    }
+
+    pub(super) fn zone(&self, index: usize) -> &Zone {
+        &self.zones[index]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Looping {
+    No,
+    Continuous,
+    /// Loops while the key is held, then plays on to the end of the sample
+    UntilRelease,
+}
+
+/// One sample, with how it is played over a range of keys and velocities
+#[derive(Debug)]
+pub(super) struct Zone {
+    keys: RangeInclusive<u8>,
+    velocities: RangeInclusive<u8>,
+    /// Every sample in the soundfont, which the positions below are in
+    data: Arc<[i16]>,
+    start: usize,
+    /// Just after the last sample
+    end: usize,
+    loop_start: usize,
+    /// Just after the last sample of the loop
+    loop_end: usize,
+    looping: Looping,
+    sample_rate: f64,
+    /// Key at which the sample plays at its own rate, before tuning
+    root_key: f64,
+    /// Frequency of `root_key`, at which the sample plays at its own rate
+    root_frequency: f64,
+    /// Semitones per key
+    scale: f64,
+    /// In semitones
+    tune: f64,
+    gain: f64,
+    pub(super) envelope: Envelope,
+}
+
+impl Zone {
+    /// Frequency that `key` sounds at
+    pub(super) fn frequency(&self, key: u8) -> f64 {
+        let semitones = (f64::from(key) - self.root_key) * self.scale + self.tune;
+        self.root_frequency * 2.0f64.powf(semitones / 12.0)
+    }
+
+    /// Where playback starts, for `sample`
+    pub(super) fn start(&self) -> f64 {
+        self.start as f64
+    }
+
+    /// The sample at `position`, which is then moved on by samples played at `frequency`.
+    /// `None` once the end is reached.
+    pub(super) fn sample(&self, position: &mut f64, frequency: f64, released: bool, sample_dt: f64) -> Option<f64> {
+        let looping = match self.looping {
+            Looping::No => false,
+            Looping::Continuous => true,
+            Looping::UntilRelease => !released,
+        };
+        if looping && *position >= self.loop_end as f64 {
+            // High notes can step over more than the whole loop in one sample
+            let loop_start = self.loop_start as f64;
+            *position = loop_start + (*position - loop_start).rem_euclid((self.loop_end - self.loop_start) as f64);
+        }
+        let index = *position as usize;
+        if index >= self.end {
+            return None;
+        }
+        let next = if looping && index + 1 == self.loop_end {
+            self.loop_start
+        } else {
+            (index + 1).min(self.end - 1)
+        };
+        let fraction = *position - index as f64;
+        let (a, b) = (f64::from(self.data[index]), f64::from(self.data[next]));
+        *position += frequency / self.root_frequency * self.sample_rate * sample_dt;
+        Some((a + (b - a) * fraction) / 32768.0 * self.gain)
+    }
+}
+
+impl SoundFont {
+    /// Parses a whole SoundFont 2 file.
+    pub fn parse(file: &[u8]) -> Result<Self, SoundFontError> {
+        let invalid = SoundFontError::Invalid;
+        let (id, body) = chunks(file)?.into_iter().next().ok_or(invalid("file is empty"))?;
+        if &id != b"RIFF" || body.get(..4) != Some(b"sfbk") {
+            return Err(invalid("not a SoundFont 2 file"));
+        }
+        let mut sample_data: &[u8] = &[];
+        let mut hydra = Hydra::default();
+        for (id, body) in chunks(&body[4..])? {
+            if &id != b"LIST" || body.len() < 4 {
+                continue;
+            }
+            for (id, body) in chunks(&body[4..])? {
+                match &id {
+                    b"smpl" => sample_data = body,
+                    b"phdr" => hydra.presets = records(body)?,
+                    b"pbag" => hydra.preset_bags = records(body)?,
+                    b"pgen" => hydra.preset_generators = records(body)?,
+                    b"inst" => hydra.instruments = records(body)?,
+                    b"ibag" => hydra.instrument_bags = records(body)?,
+                    b"igen" => hydra.instrument_generators = records(body)?,
+                    b"shdr" => hydra.samples = records(body)?,
+                    _ => {}
+                }
+            }
+        }
+
+        let (data, _) = sample_data.as_chunks::<2>();
+        let data: Arc<[i16]> = data.iter().map(|bytes| i16::from_le_bytes(*bytes)).collect();
+        let presets = hydra.presets(&data)?;
+        if presets.is_empty() {
+            return Err(invalid("no presets"));
+        }
+        Ok(SoundFont { presets })
+    }
+
+    /// Reads a SoundFont 2 file, as described for `parse`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SoundFontError> {
+        SoundFont::parse(&std::fs::read(path).map_err(SoundFontError::Io)?)
+    }
+
+    /// The presets in bank 0 that are General MIDI programs, and the percussion presets, each
+    /// with its program
+    pub(super) fn into_presets(self) -> (Vec<Program>, Vec<Program>) {
+        let mut programs = Vec::new();
+        let mut drum_kits = Vec::new();
+        for preset in self.presets {
+            match (preset.bank, u8::try_from(preset.program)) {
+                (0, Ok(program @ 0..=127)) => programs.push((program, preset)),
+                (PERCUSSION_BANK, Ok(program @ 0..=127)) => drum_kits.push((program, preset)),
+                _ => {}
+            }
+        }
+        (programs, drum_kits)
+    }
+}
+
+/// A RIFF chunk's ID and body
+type Chunk<'a> = ([u8; 4], &'a [u8]);
+
+/// Splits RIFF chunks into their IDs and bodies.
+fn chunks(mut data: &[u8]) -> Result<Vec<Chunk<'_>>, SoundFontError> {
+    let mut chunks = Vec::new();
+    while data.len() >= 8 {
+        let id = data[..4].try_into().unwrap();
+        let len = u32::from_le_bytes(data[4..8].try_into().unwrap()) as usize;
+        let body = data.get(8..8 + len).ok_or(SoundFontError::Invalid("chunk is cut short"))?;
+        chunks.push((id, body));
+        // Chunks are padded to an even length
+        data = data.get(8 + len + len % 2..).unwrap_or_default();
+    }
+    Ok(chunks)
+}
+
+fn records<const N: usize>(body: &[u8]) -> Result<Vec<[u8; N]>, SoundFontError> {
+    match body.as_chunks::<N>() {
+        (records, []) => Ok(records.to_vec()),
+        _ => Err(SoundFontError::Invalid("records are cut short")),
+    }
+}
+
+fn u16_at(record: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([record[offset], record[offset + 1]])
+}
+
+fn u32_at(record: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(record[offset..offset + 4].try_into().unwrap())
+}
+
+/// The preset, instrument and sample headers, as they are in the file
+#[derive(Default)]
+struct Hydra {
+    presets: Vec<[u8; 38]>,
+    preset_bags: Vec<[u8; 4]>,
+    preset_generators: Vec<[u8; 4]>,
+    instruments: Vec<[u8; 22]>,
+    instrument_bags: Vec<[u8; 4]>,
+    instrument_generators: Vec<[u8; 4]>,
+    samples: Vec<[u8; 46]>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Generators {
+    amounts: [i16; GENERATOR_COUNT],
+    keys: (u8, u8),
+    velocities: (u8, u8),
+    /// The instrument or sample that the zone plays, which global zones have none of
+    target: Option<u16>,
+}
+
+impl Generators {
+    /// Defaults for instrument zones, which presets' generators then add to
+    fn instrument() -> Self {
+        let mut amounts = [0; GENERATOR_COUNT];
+        for timecents in [ATTACK_VOL_ENV, DECAY_VOL_ENV, RELEASE_VOL_ENV] {
+            amounts[timecents] = -12000;
+        }
+        amounts[SCALE_TUNING] = 100;
+        amounts[OVERRIDING_ROOT_KEY] = -1;
+        Generators { amounts, keys: (0, 127), velocities: (0, 127), target: None }
+    }
+
+    fn preset() -> Self {
+        Generators { amounts: [0; GENERATOR_COUNT], ..Generators::instrument() }
+    }
+
+    /// Applies one zone's generators on top of these, with `target` being the instrument or sample
+    /// generator that ends the zone.
+    fn apply(mut self, generators: &[[u8; 4]], target: usize) -> Self {
+        for generator in generators {
+            let operator = usize::from(u16_at(generator, 0));
+            let [lo, hi] = [generator[2], generator[3]];
+            match operator {
+                KEY_RANGE => self.keys = (lo, hi),
+                VEL_RANGE => self.velocities = (lo, hi),
+                _ if operator == target => self.target = Some(u16::from_le_bytes([lo, hi])),
+                _ if operator < GENERATOR_COUNT => self.amounts[operator] = i16::from_le_bytes([lo, hi]),
+                _ => {}
+            }
+        }
+        self
+    }
+}
+
+/// Ranges of records that a bag or header refers to, up to where the next one starts
+fn span(starts: impl Fn(usize) -> Option<usize>, index: usize) -> Result<std::ops::Range<usize>, SoundFontError> {
+    match (starts(index), starts(index + 1)) {
+        (Some(start), Some(end)) if start <= end => Ok(start..end),
+        _ => Err(SoundFontError::Invalid("zone indices are out of order")),
+    }
+}
+
+/// Seconds from SF2 timecents
+fn seconds(timecents: i16) -> f64 {
+    2.0f64.powf(f64::from(timecents) / 1200.0)
+}
+
+/// Gain from SF2 centibels of attenuation
+fn gain(centibels: i16) -> f64 {
+    10.0f64.powf(-f64::from(centibels.max(0)) / 200.0)
+}
+
+impl Hydra {
+    /// Zones of each preset or instrument, as the generators of each zone in the header's bags
+    fn zones(bags: &[[u8; 4]], generators: &[[u8; 4]], bag_range: std::ops::Range<usize>, defaults: Generators, target: usize) -> Result<Vec<Generators>, SoundFontError> {
+        let mut global = defaults;
+        let mut zones = Vec::new();
+        for bag in bag_range.clone() {
+            let generator_range = span(|index| bags.get(index).map(|bag| usize::from(u16_at(bag, 0))), bag)?;
+            let zone = global.apply(
+                generators.get(generator_range).ok_or(SoundFontError::Invalid("zone generators are missing"))?,
+                target,
+            );
+            match zone.target {
+                Some(_) => zones.push(zone),
+                // Only the first zone may be global, and others without a target are ignored
+                None if bag == bag_range.start => global = zone,
+                None => {}
+            }
+        }
+        Ok(zones)
+    }
+
+    fn presets(&self, data: &Arc<[i16]>) -> Result<Vec<Preset>, SoundFontError> {
+        let invalid = SoundFontError::Invalid;
+        let mut presets = Vec::new();
+        // The last header of each list only marks where the one before it ends
+        for index in 0..self.presets.len().saturating_sub(1) {
+            let header = &self.presets[index];
+            let bag_range = span(|index| self.presets.get(index).map(|header| usize::from(u16_at(header, 24))), index)?;
+            let mut zones = Vec::new();
+            for preset_zone in Hydra::zones(&self.preset_bags, &self.preset_generators, bag_range, Generators::preset(), INSTRUMENT)? {
+                let instrument = usize::from(preset_zone.target.unwrap_or_default());
+                if instrument + 1 >= self.instruments.len() {
+                    return Err(invalid("preset refers to a missing instrument"));
+                }
+                let bag_range = span(|index| self.instruments.get(index).map(|header| usize::from(u16_at(header, 20))), instrument)?;
+                for instrument_zone in Hydra::zones(&self.instrument_bags, &self.instrument_generators, bag_range, Generators::instrument(), SAMPLE_ID)? {
+                    if let Some(zone) = self.zone(data, &preset_zone, &instrument_zone)? {
+                        zones.push(zone);
+                    }
+                }
+            }
+            presets.push(Preset { bank: u16_at(header, 22), program: u16_at(header, 20), zones });
+        }
+        Ok(presets)
+    }
+
+    /// Combines a preset zone with one of its instrument's zones, unless their ranges do not overlap.
+    fn zone(&self, data: &Arc<[i16]>, preset: &Generators, instrument: &Generators) -> Result<Option<Zone>, SoundFontError> {
+        let invalid = SoundFontError::Invalid;
+        let intersect = |(a_lo, a_hi): (u8, u8), (b_lo, b_hi): (u8, u8)| a_lo.max(b_lo)..=a_hi.min(b_hi);
+        let keys = intersect(preset.keys, instrument.keys);
+        let velocities = intersect(preset.velocities, instrument.velocities);
+        if keys.is_empty() || velocities.is_empty() {
+            return Ok(None);
+        }
+        let mut amounts = instrument.amounts;
+        for generator in ADDITIVE {
+            amounts[generator] = amounts[generator].saturating_add(preset.amounts[generator]);
+        }
+
+        let sample = usize::from(instrument.target.unwrap_or_default());
+        if sample + 1 >= self.samples.len() {
+            return Err(invalid("instrument refers to a missing sample"));
+        }
+        let header = &self.samples[sample];
+        let offset = |position: usize, fine: usize, coarse: usize| {
+            let offset = i64::from(amounts[fine]) + i64::from(amounts[coarse]) * 32768;
+            usize::try_from(position as i64 + offset).ok().filter(|position| *position <= data.len())
+        };
+        let position = |at| u32_at(header, at) as usize;
+        let (Some(start), Some(end)) = (
+            offset(position(20), START_OFFSET, START_COARSE_OFFSET),
+            offset(position(24), END_OFFSET, END_COARSE_OFFSET),
+        ) else {
+            return Err(invalid("sample is out of bounds"));
+        };
+        if start >= end {
+            return Err(invalid("sample is out of bounds"));
+        }
+        let loop_start = offset(position(28), START_LOOP_OFFSET, START_LOOP_COARSE_OFFSET).unwrap_or(start);
+        let loop_end = offset(position(32), END_LOOP_OFFSET, END_LOOP_COARSE_OFFSET).unwrap_or(end);
+        let loop_valid = start <= loop_start && loop_start < loop_end && loop_end <= end;
+        let looping = match amounts[SAMPLE_MODES] & 3 {
+            1 if loop_valid => Looping::Continuous,
+            3 if loop_valid => Looping::UntilRelease,
+            _ => Looping::No,
+        };
+        let sample_rate = u32_at(header, 36);
+        if sample_rate == 0 {
+            return Err(invalid("sample has no sample rate"));
+        }
+
+        let root_key = match amounts[OVERRIDING_ROOT_KEY] {
+            key @ 0..=127 => key as u8,
+            _ => header[40].min(127),
+        };
+        let pitch_correction = header[41] as i8;
+        Ok(Some(Zone {
+            keys,
+            velocities,
+            data: Arc::clone(data),
+            start,
+            end,
+            loop_start,
+            loop_end,
+            looping,
+            sample_rate: f64::from(sample_rate),
+            root_key: f64::from(root_key),
+            root_frequency: 440.0 * 2.0f64.powf((f64::from(root_key) - 69.0) / 12.0),
+            scale: f64::from(amounts[SCALE_TUNING]) / 100.0,
+            tune: f64::from(amounts[COARSE_TUNE]) + (f64::from(amounts[FINE_TUNE]) + f64::from(pitch_correction)) / 100.0,
+            gain: gain(amounts[INITIAL_ATTENUATION]),
+            envelope: Envelope {
+                attack: seconds(amounts[ATTACK_VOL_ENV]),
+                decay: seconds(amounts[DECAY_VOL_ENV]),
+                sustain: gain(amounts[SUSTAIN_VOL_ENV]),
+                release: seconds(amounts[RELEASE_VOL_ENV]),
+            },
+        }))
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    fn chunk(id: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut chunk = id.to_vec();
+        chunk.extend((body.len() as u32).to_le_bytes());
+        chunk.extend(body);
+        if body.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    fn list(kind: &[u8; 4], chunks: &[Vec<u8>]) -> Vec<u8> {
+        chunk(b"LIST", &[kind.as_slice(), &chunks.concat()].concat())
+    }
+
+    fn name(name: &str) -> Vec<u8> {
+        let mut bytes = name.as_bytes().to_vec();
+        bytes.resize(20, 0);
+        bytes
+    }
+
+    fn generator(operator: usize, amount: i16) -> [u8; 4] {
+        let [lo, hi] = amount.to_le_bytes();
+        [operator as u8, 0, lo, hi]
+    }
+
+    fn range(operator: usize, lo: u8, hi: u8) -> [u8; 4] {
+        [operator as u8, 0, lo, hi]
+    }
+
+    /// Bags and generators for a list of zones, with the terminal records after them
+    fn bags(zones: &[&[[u8; 4]]]) -> (Vec<u8>, Vec<u8>) {
+        let (mut bags, mut generators) = (Vec::new(), Vec::new());
+        for zone in zones.iter().chain([&[][..]].iter()) {
+            bags.extend(((generators.len() / 4) as u16).to_le_bytes());
+            bags.extend([0, 0]);
+            generators.extend(zone.concat());
+        }
+        generators.extend([0; 4]);
+        (bags, generators)
+    }
+
+    fn sample_header(name_: &str, start: u32, end: u32, loop_points: (u32, u32)) -> Vec<u8> {
+        let mut header = name(name_);
+        for position in [start, end, loop_points.0, loop_points.1, 44100] {
+            header.extend(position.to_le_bytes());
+        }
+        // Original pitch A4, no correction, no link, mono
+        header.extend([69, 0, 0, 0, 1, 0]);
+        header
+    }
+
+    /// A small soundfont. Preset 0 is a sine wave at 441 Hz that loops, layered an octave up at
+    /// high keys and velocities. Drum kit 0 is a click that does not loop, and kit 25 plays the
+    /// same click on key 35 only.
+    pub(in super::super) fn fixture() -> Vec<u8> {
+        let mut samples: Vec<i16> = (0..1000).map(|i| ((i as f64 * std::f64::consts::TAU / 100.0).sin() * 16000.0) as i16).collect();
+        samples.extend([0; 46]);
+        samples.extend([16000; 50]);
+        samples.extend([0; 46]);
+        let samples: Vec<u8> = samples.iter().flat_map(|sample| sample.to_le_bytes()).collect();
+        let sample_headers = [
+            sample_header("Sine", 0, 1000, (900, 1000)),
+            sample_header("Click", 1046, 1096, (1046, 1096)),
+            sample_header("EOS", 0, 0, (0, 0)),
+        ];
+
+        let (instrument_bags, instrument_generators) = bags(&[
+            // Global zone: a one second release, and sustain 6 dB down
+            &[generator(RELEASE_VOL_ENV, 0), generator(SUSTAIN_VOL_ENV, 60)],
+            &[range(KEY_RANGE, 0, 63), generator(SAMPLE_MODES, 1), generator(SAMPLE_ID, 0)],
+            &[range(KEY_RANGE, 60, 127), range(VEL_RANGE, 100, 127), generator(COARSE_TUNE, 12), generator(SAMPLE_MODES, 1), generator(SAMPLE_ID, 0)],
+            &[generator(SAMPLE_ID, 1)],
+        ]);
+        let instruments = [[name("Sine"), vec![0, 0]].concat(), [name("Click"), vec![3, 0]].concat(), [name("EOI"), vec![4, 0]].concat()];
+
+        let (preset_bags, preset_generators) = bags(&[
+            &[generator(INSTRUMENT, 0)],
+            &[range(KEY_RANGE, 35, 81), generator(INSTRUMENT, 1)],
+            &[range(KEY_RANGE, 35, 35), generator(INSTRUMENT, 1)],
+        ]);
+        let preset_header = |name_: &str, program: u16, bank: u16, bag: u16| {
+            [name(name_), [program.to_le_bytes(), bank.to_le_bytes(), bag.to_le_bytes()].concat(), vec![0; 12]].concat()
+        };
+        let presets = [
+            preset_header("Sine", 0, 0, 0),
+            preset_header("Drums", 0, PERCUSSION_BANK, 1),
+            preset_header("Room", 25, PERCUSSION_BANK, 2),
+            preset_header("EOP", 0, 0, 3),
+        ];
+
+        let body = [
+            b"sfbk".to_vec(),
+            list(b"INFO", &[chunk(b"ifil", &[2, 0, 1, 0])]),
+            list(b"sdta", &[chunk(b"smpl", &samples)]),
+            list(b"pdta", &[
+                chunk(b"phdr", &presets.concat()),
+                chunk(b"pbag", &preset_bags),
+                chunk(b"pmod", &[0; 10]),
+                chunk(b"pgen", &preset_generators),
+                chunk(b"inst", &instruments.concat()),
+                chunk(b"ibag", &instrument_bags),
+                chunk(b"imod", &[0; 10]),
+                chunk(b"igen", &instrument_generators),
+                chunk(b"shdr", &sample_headers.concat()),
+            ]),
+        ];
+        chunk(b"RIFF", &body.concat())
+    }
+
+    #[test]
+    fn zones() {
+        let (programs, drum_kits) = SoundFont::parse(&fixture()).unwrap().into_presets();
+        let [(0, preset)] = &programs[..] else { panic!("expected only program 0") };
+        assert_eq!(preset.zones(61, 64).count(), 1);
+        assert_eq!(preset.zones(61, 110).count(), 2);
+        assert_eq!(preset.zones(70, 64).count(), 0);
+        let [(0, standard), (25, room)] = &drum_kits[..] else { panic!("expected drum kits 0 and 25") };
+        assert_eq!(standard.zones(30, 64).count(), 0);
+        assert_eq!(standard.zones(36, 64).count(), 1);
+        assert_eq!(room.zones(36, 64).count(), 0);
+
+        let layers: Vec<_> = preset.zones(61, 110).map(|(_, zone)| zone).collect();
+        // The global zone applies to both
+        assert!(layers.iter().all(|zone| zone.envelope.release == 1.0 && (zone.envelope.sustain - 0.5).abs() < 0.01));
+        assert_eq!(layers[0].frequency(69), 440.0);
+        assert_eq!(layers[1].frequency(69), 880.0);
+        assert_eq!(layers[1].frequency(57), 440.0);
+    }
+
+    #[test]
+    fn playback() {
+        let (programs, drum_kits) = SoundFont::parse(&fixture()).unwrap().into_presets();
+        let (_, sine) = programs[0].1.zones(61, 64).next().unwrap();
+        let mut position = sine.start();
+        let played: Vec<f64> = (0..5000).map_while(|_| sine.sample(&mut position, 440.0, false, 1.0 / 44100.0)).collect();
+        // Loops for as long as the note is held, at the sample's own rate
+        assert_eq!(played.len(), 5000);
+        assert!((played[4025] - played[25]).abs() < 1e-9);
+        assert!((played[25] - 16000.0 / 32768.0).abs() < 0.01);
+        // Keeps looping when each step is longer than the loop
+        let mut position = sine.start();
+        let played = (0..1000).map_while(|_| sine.sample(&mut position, 440.0 * 150.0, false, 1.0 / 44100.0)).count();
+        assert_eq!(played, 1000);
+
+        let (_, click) = drum_kits[0].1.zones(36, 64).next().unwrap();
+        let mut position = click.start();
+        let played = std::iter::from_fn(|| click.sample(&mut position, 440.0, false, 1.0 / 44100.0)).count();
+        assert_eq!(played, 50);
+    }
+
+    #[test]
+    fn invalid_files() {
+        let error = |file: &[u8]| SoundFont::parse(file).unwrap_err().to_string();
+        assert_eq!(error(b""), "invalid soundfont: file is empty");
+        assert_eq!(error(&chunk(b"RIFF", b"WAVE")), "invalid soundfont: not a SoundFont 2 file");
+        let mut file = fixture();
+        file.truncate(file.len() - 10);
+        assert_eq!(error(&file), "invalid soundfont: chunk is cut short");
+        assert!(matches!(SoundFont::load("/nonexistent.sf2"), Err(SoundFontError::Io(_))));
+    }
+}
@@ -0,0 +1,305 @@
+//! The synthesizer: channel state, voices and drums, driven by MIDI messages and rendered to samples.
+
+use std::sync::Arc;
+
+use midly::live::LiveEvent;
+
+use super::{midi, Bank, percussion, voices, Channel, Note, CHANNEL_COUNT, DRUM_NOTE_COUNT, PERCUSSION_CHANNEL, SOFT_PEDAL_GAIN};
+
+/// Partials fade out over this fraction of the Nyquist frequency below it, since above it they
+/// would alias back down to lower frequencies
+const BAND_LIMIT_FADE: f64 = 0.2;
+
+/// A General MIDI synthesizer with 16 channels, rendering stereo audio.
+pub struct Synth {
+    /// Samples per second
+    rate: u32,
+    sample_dt: f64,
+    channels: [Channel; CHANNEL_COUNT],
+    bank: Bank,
+    voices: voices::Voices,
+    drums: [percussion::DrumNote; DRUM_NOTE_COUNT * 2],
+    /// For bytes given to `handle_midi`
+    parser: midi::MidiParser,
+}
+
+impl Synth {
+    /// A synthesizer rendering `rate` samples per second, with up to `polyphony` melodic notes
+    /// sounding at once.
+    ///
+    /// # Panics
+    ///
+    /// If `rate` or `polyphony` is zero.
+    pub fn new(rate: u32, polyphony: usize, steal_policy: voices::StealPolicy) -> Self {
+        assert!(rate > 0, "sample rate must be positive");
+        Synth {
+            rate,
+            sample_dt: 1.0 / rate as f64,
+            channels: std::array::from_fn(|_| Channel::default()),
+            bank: Bank::builtin(),
+            voices: voices::Voices::new(polyphony, steal_policy),
+            drums: [percussion::DrumNote::default(); DRUM_NOTE_COUNT * 2],
+            parser: midi::MidiParser::new(),
+        }
+    }
+
+    /// Samples per second
+    pub fn rate(&self) -> u32 {
+        self.rate
+    }
+
+    /// Uses the instruments from `bank`, for channels' current programs too.
+    /// Notes that are already playing keep their instrument.
+    pub fn set_bank(&mut self, bank: Bank) {
+        for channel in &mut self.channels {
+            channel.instrument = Arc::clone(bank.program(channel.program));
+        }
+        self.bank = bank;
+    }
+
+    /// Whether nothing is sounding, so rendering would only produce silence
+    pub fn is_silent(&self) -> bool {
+        self.voices.iter().next().is_none() && self.drums.iter().all(|drum_note| drum_note.sample_time == 0)
+    }
+
+    /// Applies MIDI 1.0 bytes, as they would arrive over a wire. Messages may be split across calls,
+    /// and running status carries over from one call to the next.
+    pub fn handle_midi(&mut self, bytes: &[u8]) {
+        let mut parser = std::mem::take(&mut self.parser);
+        for &byte in bytes {
+            if let Some(message) = parser.push(byte) {
+                self.handle_message(message);
+            }
+        }
+        self.parser = parser;
+    }
+
+    /// Applies one MIDI event. This does not affect the running status of `handle_midi`.
+    pub fn handle_event(&mut self, event: LiveEvent) {
+        let mut message = Vec::with_capacity(3);
+        // Writing to a Vec cannot fail
+        if event.write_std(&mut message).is_ok() {
+            self.handle_message(&message);
+        }
+    }
+
+    /// Applies one whole MIDI message, as returned by `MidiParser::push`.
+    fn handle_message(&mut self, message: &[u8]) {
+        match *message {
+            [status @ 0x80..=0x9F, note, velocity] => {
+                // Note-off or Note-on
+                let channel = (status & 0x0f) as usize;
+                let off = status < 0x90 || velocity == 0;
+                // println!("channel {channel} {on}, {note}, {velocity}", on = if off {"off"} else {"on"});
+
+                // A soundfont's drums play like any other preset
+                let drum_kit = self.bank.drum_kit(self.channels[channel].program).filter(|_| channel == PERCUSSION_CHANNEL);
+                if channel == PERCUSSION_CHANNEL && drum_kit.is_none() {
+                    // Drums play out on their own; note-offs do not stop them
+                    let Some(drum) = percussion::drum(note).filter(|_| !off) else {
+                        return;
+                    };
+                    for drum_note in &mut self.drums {
+                        drum_note.choke(drum);
+                    }
+                    percussion::start(&mut self.drums, DRUM_NOTE_COUNT, note, velocity as u32 * 8192 / 0xff, drum);
+                    return;
+                }
+
+                if off {
+                    let playing = self.voices.channel(channel).find(|note_info| {
+                        note_info.note == note && note_info.stop_time == 0 && !note_info.key_up
+                    });
+                    // Every layer of the note
+                    let Some(age) = playing.map(|note_info| note_info.age) else {
+                        return;
+                    };
+                    for note_info in self.voices.channel(channel).filter(|note_info| note_info.age == age) {
+                        if self.channels[channel].holds(note_info) {
+                            note_info.key_up = true;
+                        } else {
+                            note_info.release();
+                        }
+                    }
+                } else {
+                    let mut amp = velocity as u32 * 8192 / 0xff;
+                    if self.channels[channel].soft {
+                        amp = (amp as f64 * SOFT_PEDAL_GAIN) as u32;
+                    }
+                    let note_info = Note {
+                        note,
+                        amp,
+                        freq: 440.0 * (2.0f64).powf((note as f64 - 69.0) / 12.0),
+                        sample_time: 1,
+                        instrument: Arc::clone(&self.channels[channel].instrument),
+                        envelope: self.channels[channel].instrument.envelope.to_samples(self.rate),
+                        channel,
+                        noise: percussion::Noise::new(0x9E37_79B9 ^ (u32::from(note) << 8) ^ u32::from(velocity)),
+                        ..Note::default()
+                    };
+                    let Some(preset) = drum_kit.or_else(|| self.bank.preset(self.channels[channel].program)) else {
+                        self.voices.start(note_info);
+                        return;
+                    };
+                    // A voice for each sample in the preset that plays at this key and velocity
+                    for (layer, (index, zone)) in preset.zones(note, velocity).enumerate() {
+                        let note_info = Note {
+                            freq: zone.frequency(note),
+                            envelope: zone.envelope.to_samples(self.rate),
+                            zone: Some((Arc::clone(preset), index)),
+                            position: zone.start(),
+                            ..note_info.clone()
+                        };
+                        if layer == 0 {
+                            self.voices.start(note_info);
+                        } else {
+                            self.voices.layer(note_info);
+                        }
+                    }
+                }
+            }
+            [status @ 0xC0..=0xCF, program] => {
+                // Program change
+                let channel = (status & 0x0f) as usize;
+                // println!("Channel {channel} program change to {program}");
+                self.channels[channel].program = program;
+                self.channels[channel].instrument = Arc::clone(self.bank.program(program));
+            }
+            [status @ 0xB0..=0xBF, controller, value] => {
+                // Control change
+                let channel = (status & 0x0f) as usize;
+                let had_sostenuto = self.channels[channel].sostenuto;
+                self.channels[channel].controller(controller, value);
+                match controller {
+                    // All Sound Off
+                    120 => {
+                        self.voices.channel(channel).for_each(Note::fade_out);
+                        if channel == PERCUSSION_CHANNEL {
+                            self.drums.iter_mut().for_each(percussion::DrumNote::fade_out);
+                        }
+                    }
+                    // All Notes Off, and the mode changes that imply it. Pedals still hold notes.
+                    123..=127 => self.voices.channel(channel).for_each(|note| note.key_up = true),
+                    _ => {}
+                }
+                let sostenuto_pressed = !had_sostenuto && self.channels[channel].sostenuto;
+                self.channels[channel].update_pedals(self.voices.channel(channel), sostenuto_pressed);
+            }
+            [status @ 0xE0..=0xEF, lsb, msb] => {
+                // Pitch bend, 14 bits, LSB first
+                let channel = (status & 0x0f) as usize;
+                let bend = lsb as u16 | ((msb as u16) << 7);
+                self.channels[channel].pitch_bend = (bend as f64 - 8192.0) / 8192.0;
+            }
+            // Aftertouch, SysEx, system common and real-time messages are not used
+            _ => {}
+        }
+    }
+
+    /// Fills `buf` with interleaved left and right samples, from -1.0 to 1.0.
+    ///
+    /// # Panics
+    ///
+    /// If `buf` has an odd length, so that it does not hold whole frames.
+    pub fn render(&mut self, buf: &mut [f32]) {
+        assert!(buf.len().is_multiple_of(2), "buffer must hold whole stereo frames");
+        let mut ampsum = 0;
+        for note in self.voices.iter() {
+            ampsum += note.amp;
+        }
+        for drum_note in &self.drums {
+            if drum_note.sample_time != 0 {
+                ampsum += drum_note.amp;
+            }
+        }
+
+        let bend_factors = self.channels.each_ref().map(Channel::bend_factor);
+        let gains = self.channels.each_ref().map(Channel::gains);
+        let nyquist = self.rate as f64 / 2.0;
+
+        for frame in buf.chunks_exact_mut(2) {
+            let mut wav = [0.0; CHANNEL_COUNT];
+
+            for note in self.voices.iter_mut() {
+                if note.stop_time != 0 && note.sample_time >= note.stop_time {
+                    note.sample_time = 0;
+                    continue;
+                }
+                note.sample_time += 1;
+
+                let env = match note.envelope.envelope(note.sample_time, note.stop_time) {
+                    Ok(env) => env * note.steal_fade(self.sample_dt),
+                    Err(_) => {
+                        note.sample_time = 0;
+                        continue;
+                    }
+                };
+                if env <= 0.0 {
+                    // Finished fading out after being stolen
+                    note.sample_time = 0;
+                    continue;
+                }
+                note.level = note.amp as f64 * env;
+
+                if let Some((preset, index)) = &note.zone {
+                    let zone = preset.zone(*index);
+                    let frequency = note.freq * bend_factors[note.channel];
+                    let Some(sample) = zone.sample(&mut note.position, frequency, note.stop_time != 0, self.sample_dt) else {
+                        // Played to the end of a sample that does not loop
+                        note.sample_time = 0;
+                        continue;
+                    };
+                    wav[note.channel] += note.level * sample;
+                    continue;
+                }
+
+                let instrument = &note.instrument;
+                // Seconds since the note started
+                let time = note.sample_time as f64 * self.sample_dt;
+                let (tremolo_rate, tremolo_depth) = instrument.tremolo;
+                note.level *= 1.0 - tremolo_depth * 0.5 * (1.0 - (std::f64::consts::TAU * tremolo_rate * time).cos());
+
+                let mut wava = 0.0;
+                let (vibrato_rate, vibrato_depth) = instrument.vibrato;
+                let vibrato = if vibrato_depth == 0.0 {
+                    1.0
+                } else {
+                    2.0f64.powf(vibrato_depth / 12.0 * (std::f64::consts::TAU * vibrato_rate * time).sin())
+                };
+                let frequency = note.freq * bend_factors[note.channel] * vibrato;
+                note.current_parameter += std::f64::consts::TAU * self.sample_dt * frequency;
+
+                // Each partial is damped by this much more than the one below it
+                let damping = if instrument.damping > 0.0 { (-time / instrument.damping).exp() } else { 1.0 };
+                let mut partial_gain = 1.0;
+                for (i, amp) in instrument.amplitudes.iter().copied().enumerate() {
+                    let ratio = instrument.ratios.get(i).copied().unwrap_or((i + 1) as f64);
+                    let band_limit = ((nyquist - ratio * frequency) / (nyquist * BAND_LIMIT_FADE)).clamp(0.0, 1.0);
+                    if band_limit > 0.0 {
+                        let parameter = ratio * note.current_parameter;
+                        wava += amp * partial_gain * band_limit * parameter.sin();
+                        // wava += amp * opt_sin(parameter);
+                    }
+                    partial_gain *= damping;
+                }
+                if instrument.noise > 0.0 {
+                    let band = (note.freq * 0.5, (note.freq * 8.0).min(self.rate as f64 * 0.45));
+                    wava += instrument.noise * note.noise.sample(band, self.sample_dt);
+                }
+                wav[note.channel] += note.level * wava;
+            }
+            for drum_note in &mut self.drums {
+                wav[PERCUSSION_CHANNEL] += drum_note.next_sample(self.sample_dt);
+            }
+
+            let (mut left, mut right) = (0.0, 0.0);
+            for (channel_wav, (left_gain, right_gain)) in wav.into_iter().zip(gains) {
+                left += channel_wav * left_gain;
+                right += channel_wav * right_gain;
+            }
+            let scale = if ampsum > 32767 { 1.0 / ampsum as f64 } else { 1.0 / 32767.0 };
+            frame[0] = (left * scale).clamp(-1.0, 1.0) as f32;
+            frame[1] = (right * scale).clamp(-1.0, 1.0) as f32;
+        }
+    }
+}
@@ -0,0 +1,102 @@
+//! One pool of voices shared by all melodic channels, stealing voices when it is full.
+
+use super::Note;
+
+/// How long a stolen voice takes to fade out, in seconds
+const STEAL_FADE: f64 = 0.005;
+
+/// Which voice to take over when a note starts and the pool is full
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StealPolicy {
+    /// The voice that started first
+    Oldest,
+    /// The voice that is currently softest
+    Quietest,
+    /// The voice closest to the end of its release, or the oldest if none are releasing
+    #[default]
+    Releasing,
+}
+
+#[derive(Debug)]
+pub(super) struct Voices {
+    voices: Vec<Note>,
+    /// How many voices may sound at once, not counting stolen voices that are fading out
+    polyphony: usize,
+    policy: StealPolicy,
+    /// Counts started notes, to tell which voices are oldest
+    started: u64,
+}
+
+impl Voices {
+    pub(super) fn new(polyphony: usize, policy: StealPolicy) -> Self {
+        assert!(polyphony > 0, "polyphony must be at least 1");
+        Voices {
+            voices: Vec::with_capacity(polyphony * 2),
+            polyphony,
+            policy,
+            started: 0,
+        }
+    }
+
+    /// Starts a note, stealing a voice first if there are too many sounding.
+    pub(super) fn start(&mut self, note: Note) {
+        self.started += 1;
+        self.push(note);
+    }
+
+    /// Starts another voice for the note that was last started, such as another layer of a
+    /// soundfont preset. Voices of the same note have the same age.
+    pub(super) fn layer(&mut self, note: Note) {
+        self.push(note);
+    }
+
+    fn push(&mut self, note: Note) {
+        self.voices.retain(|voice| voice.sample_time != 0);
+        if self.voices.iter().filter(|voice| voice.stolen_at == 0).count() >= self.polyphony {
+            let candidates = self.voices.iter_mut().filter(|voice| voice.stolen_at == 0);
+            let victim = match self.policy {
+                StealPolicy::Oldest => candidates.min_by_key(|voice| voice.age),
+                StealPolicy::Quietest => candidates.min_by(|a, b| a.level.total_cmp(&b.level)),
+                StealPolicy::Releasing => candidates.min_by_key(|voice| {
+                    (voice.stop_time == 0, voice.stop_time.saturating_sub(voice.sample_time), voice.age)
+                }),
+            };
+            if let Some(victim) = victim {
+                victim.fade_out();
+            }
+        }
+        self.voices.push(Note { age: self.started, stolen_at: 0, ..note });
+    }
+
+    /// Voices that are playing on this channel and have not been stolen
+    pub(super) fn channel(&mut self, channel: usize) -> impl Iterator<Item = &mut Note> {
+        self.voices
+            .iter_mut()
+            .filter(move |voice| voice.channel == channel && voice.sample_time != 0 && voice.stolen_at == 0)
+    }
+
+    pub(super) fn iter(&self) -> impl Iterator<Item = &Note> {
+        self.voices.iter().filter(|voice| voice.sample_time != 0)
+    }
+
+    pub(super) fn iter_mut(&mut self) -> impl Iterator<Item = &mut Note> {
+        self.voices.iter_mut().filter(|voice| voice.sample_time != 0)
+    }
+}
+
+impl Note {
+    /// Stops this note quickly, without a click, and frees its voice for another note.
+    pub(super) fn fade_out(&mut self) {
+        if self.stolen_at == 0 {
+            self.stolen_at = self.sample_time;
+        }
+    }
+
+    /// How loud a stolen voice still is, from 1.0 when it was stolen down to 0.0
+    pub(super) fn steal_fade(&self, sample_dt: f64) -> f64 {
+        if self.stolen_at == 0 {
+            return 1.0;
+        }
+        1.0 - (self.sample_time - self.stolen_at) as f64 * sample_dt / STEAL_FADE
+    }
+}
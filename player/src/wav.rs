@@ -0,0 +1,82 @@
+//! Writes WAV files, with 16-bit PCM or 32-bit float samples.
+
+use std::io::{self, Seek, SeekFrom, Write};
+
+use super::output::SampleFormat;
+
+pub(super) struct WavWriter<W: Write + Seek> {
+    out: W,
+    format: SampleFormat,
+    /// Bytes per frame
+    block_align: u16,
+    /// Bytes of sample data written so far
+    data_len: u32,
+    /// Bytes before the sample data
+    header_len: u32,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    /// Writes the header. The lengths in it are filled in by `finish`.
+    pub(super) fn new(mut out: W, rate: u32, channels: u16, format: SampleFormat) -> io::Result<Self> {
+        let sample_len = format.sample_len() as u16;
+        let block_align = channels * sample_len;
+        let (format_tag, fmt_len): (u16, u32) = match format {
+            SampleFormat::S16 => (1, 16),
+            // IEEE float, with the extension size that formats other than PCM have
+            SampleFormat::F32 => (3, 18),
+        };
+        out.write_all(b"RIFF")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        out.write_all(b"WAVE")?;
+        out.write_all(b"fmt ")?;
+        out.write_all(&fmt_len.to_le_bytes())?;
+        out.write_all(&format_tag.to_le_bytes())?;
+        out.write_all(&channels.to_le_bytes())?;
+        out.write_all(&rate.to_le_bytes())?;
+        out.write_all(&(rate * block_align as u32).to_le_bytes())?;
+        out.write_all(&block_align.to_le_bytes())?;
+        out.write_all(&(sample_len * 8).to_le_bytes())?;
+        let mut header_len = 36;
+        if format == SampleFormat::F32 {
+            out.write_all(&0u16.to_le_bytes())?;
+            // Formats other than PCM also have the length in frames, which is filled in by `finish`
+            out.write_all(b"fact")?;
+            out.write_all(&4u32.to_le_bytes())?;
+            out.write_all(&0u32.to_le_bytes())?;
+            header_len += 2 + 12;
+        }
+        out.write_all(b"data")?;
+        out.write_all(&0u32.to_le_bytes())?;
+        header_len += 8;
+        Ok(WavWriter { out, format, block_align, data_len: 0, header_len })
+    }
+
+    /// Writes interleaved samples.
+    pub(super) fn write(&mut self, samples: &[f32]) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(samples.len() * self.format.sample_len());
+        self.format.encode(samples, &mut bytes);
+        let data_len = u32::try_from(bytes.len()).ok().and_then(|len| self.data_len.checked_add(len));
+        // The RIFF length has to fit in 32 bits too
+        let Some(data_len) = data_len.filter(|data_len| *data_len <= u32::MAX - self.header_len) else {
+            return Err(io::Error::other("WAV file is too long"));
+        };
+        self.out.write_all(&bytes)?;
+        self.data_len = data_len;
+        Ok(())
+    }
+
+    /// Fills in the lengths in the header.
+    pub(super) fn finish(mut self) -> io::Result<W> {
+        self.out.seek(SeekFrom::Start(4))?;
+        self.out.write_all(&(self.header_len - 8 + self.data_len).to_le_bytes())?;
+        if self.format == SampleFormat::F32 {
+            // The fact chunk's length in frames
+            self.out.seek(SeekFrom::Start(46))?;
+            self.out.write_all(&(self.data_len / self.block_align as u32).to_le_bytes())?;
+        }
+        self.out.seek(SeekFrom::Start(self.header_len as u64 - 4))?;
+        self.out.write_all(&self.data_len.to_le_bytes())?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}